@@ -1,8 +1,174 @@
-use std::path::PathBuf;
+use std::{
+    fs::write,
+    path::{Path, PathBuf},
+};
 
+use ::config::{Config, File};
+use color_eyre::Result;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+/// how mods get disabled
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DisableMode {
+    /// move the mod into `profiles/<profile>/disabled`
+    #[default]
+    Move,
+    /// rename the mod folder to start with a `.` in place, smapi skips those
+    DotPrefix,
+}
+
+/// where the mods of inactive profiles live
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProfileStorage {
+    /// move the folders into `profiles/<profile>/enabled`
+    #[default]
+    Move,
+    /// keep every mod once in `SVMM/library` and link the mods of the active profile into the mods folder
+    Library,
+}
+
+/// how library mods get into the mods folder, each one falls back to the next when the filesystem refuses
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkMethod {
+    #[default]
+    Symlink,
+    Hardlink,
+    Copy,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct SVMMConfig {
     pub installation_path: PathBuf,
+    /// personal api key from https://www.nexusmods.com/users/myaccount?tab=api
+    #[serde(default)]
+    pub nexus_api_key: Option<String>,
+    /// overrides the nexus api base url, handy for pointing at a local mock server
+    #[serde(default)]
+    pub nexus_api_url: Option<String>,
+    /// overrides the smapi.io mods endpoint
+    #[serde(default)]
+    pub smapi_api_url: Option<String>,
+    /// how long smapi.io responses are trusted before asking again, defaults to a day
+    #[serde(default)]
+    pub smapi_cache_ttl_hours: Option<u64>,
+    /// how many operations can be undone, defaults to 50
+    #[serde(default)]
+    pub history_size: Option<usize>,
+    /// how many replaced versions of each mod are kept for rolling back, defaults to 5
+    #[serde(default)]
+    pub versions_kept: Option<usize>,
+    /// overrides where the game keeps its saves
+    #[serde(default)]
+    pub saves_path: Option<PathBuf>,
+    /// how many backups of each save are kept, defaults to 10
+    #[serde(default)]
+    pub save_backups_kept: Option<usize>,
+    /// language code like `de` or `de-DE`, defaults to the system locale
+    #[serde(default)]
+    pub language: Option<String>,
+    /// `move` or `dot_prefix`, defaults to moving disabled mods into the profile folder
+    #[serde(default)]
+    pub disable_mode: DisableMode,
+    /// `move` or `library`, defaults to moving the mod folders in and out of the profile folders
+    #[serde(default)]
+    pub profile_storage: ProfileStorage,
+    /// `symlink`, `hardlink` or `copy` for library profiles, defaults to symlinks
+    #[serde(default)]
+    pub link_method: LinkMethod,
+    /// file name without `.ron` of the theme picked on the About tab, defaults to following the system color scheme
+    #[serde(default)]
+    pub theme: Option<String>,
+}
+
+/// changes settings in the config file at `path`. Only the file is read, so `SVMM_` variables don't end up in it
+pub fn update_config_file(path: &Path, installation_path: &Path, update: impl FnOnce(&mut SVMMConfig)) -> Result<()> {
+    let mut config: SVMMConfig = Config::builder()
+        .add_source(File::from(path).required(false))
+        .set_default("installation_path", installation_path.to_string_lossy().to_string())?
+        .build()?
+        .try_deserialize()?;
+    update(&mut config);
+    write_config_file(path, &config)
+}
+
+/// writes `config` to `path` in a form the config crate reads back
+pub fn write_config_file(path: &Path, config: &SVMMConfig) -> Result<()> {
+    // ron writes enum variants as bare identifiers, which the config crate can't read back. Json keeps them strings
+    let mut value = serde_json::to_value(config)?;
+    if let serde_json::Value::Object(fields) = &mut value {
+        fields.retain(|_, field| !field.is_null());
+    }
+    write(path, ron::ser::to_string_pretty(&value, Default::default())?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(path: &Path) -> SVMMConfig {
+        Config::builder()
+            .add_source(File::from(path))
+            .build()
+            .unwrap()
+            .try_deserialize()
+            .unwrap()
+    }
+
+    #[test]
+    fn updates_keep_the_other_settings() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("svmm.ron");
+        write(
+            &path,
+            r#"(installation_path: "/games/Stardew Valley", history_size: Some(20), disable_mode: "dot_prefix")"#,
+        )
+        .unwrap();
+
+        update_config_file(&path, Path::new("/elsewhere"), |config| {
+            config.theme = Some("light".to_owned())
+        })
+        .unwrap();
+        let config = read(&path);
+        assert_eq!(config.installation_path, PathBuf::from("/games/Stardew Valley"));
+        assert_eq!(config.history_size, Some(20));
+        assert_eq!(config.disable_mode, DisableMode::DotPrefix);
+        assert_eq!(config.theme.as_deref(), Some("light"));
+
+        update_config_file(&path, Path::new("/elsewhere"), |config| config.theme = None).unwrap();
+        let config = read(&path);
+        assert_eq!(config.theme, None);
+        assert_eq!(config.disable_mode, DisableMode::DotPrefix);
+    }
+
+    #[test]
+    fn default_config_reads_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("svmm.ron");
+
+        write_config_file(&path, &SVMMConfig::default()).unwrap();
+        let config = read(&path);
+        assert_eq!(config.installation_path, PathBuf::new());
+        assert_eq!(config.disable_mode, DisableMode::Move);
+        assert_eq!(config.profile_storage, ProfileStorage::Move);
+        assert_eq!(config.link_method, LinkMethod::Symlink);
+        assert_eq!(config.theme, None);
+    }
+
+    #[test]
+    fn creates_a_missing_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("svmm.ron");
+
+        update_config_file(&path, Path::new("/games/Stardew Valley"), |config| {
+            config.theme = Some("dark".to_owned())
+        })
+        .unwrap();
+        let config = read(&path);
+        assert_eq!(config.installation_path, PathBuf::from("/games/Stardew Valley"));
+        assert_eq!(config.theme.as_deref(), Some("dark"));
+    }
 }
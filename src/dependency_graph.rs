@@ -0,0 +1,452 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt::Write,
+};
+
+use crate::InstalledMod;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EdgeKind {
+    Required,
+    Optional,
+    ContentPack,
+}
+
+#[derive(Clone, Debug)]
+pub struct Node {
+    pub id: String,
+    pub name: String,
+    pub active: bool,
+}
+
+/// `from` depends on `target_id`, `to` is `None` when no installed mod has that id
+#[derive(Clone, Debug)]
+pub struct Edge {
+    pub from: usize,
+    pub to: Option<usize>,
+    pub target_id: String,
+    pub kind: EdgeKind,
+}
+
+impl Edge {
+    pub fn required(&self) -> bool {
+        self.kind != EdgeKind::Optional
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct DependencyGraph {
+    pub nodes: Vec<Node>,
+    pub edges: Vec<Edge>,
+    index: HashMap<String, usize>,
+}
+
+/// unique ids are case insensitive in smapi
+fn key(id: &str) -> String {
+    id.trim().to_ascii_lowercase()
+}
+
+impl DependencyGraph {
+    pub fn new(mods: &[InstalledMod]) -> Self {
+        let mut graph = DependencyGraph::default();
+
+        for imod in mods {
+            // the same mod can be both enabled and disabled, the first one wins
+            if graph.index.contains_key(&key(&imod.manifest.unique_id)) {
+                continue;
+            }
+            graph.index.insert(key(&imod.manifest.unique_id), graph.nodes.len());
+            graph.nodes.push(Node {
+                id: imod.manifest.unique_id.trim().to_owned(),
+                name: imod.manifest.name.clone(),
+                active: imod.active,
+            });
+        }
+
+        let mut seen = HashSet::new();
+        for imod in mods {
+            let from = graph.index[&key(&imod.manifest.unique_id)];
+            if !seen.insert(from) {
+                continue;
+            }
+
+            let dependencies = imod.manifest.dependencies.iter().map(|dependency| {
+                let kind = if dependency.required {
+                    EdgeKind::Required
+                } else {
+                    EdgeKind::Optional
+                };
+                (dependency.unique_id.trim(), kind)
+            });
+            let content_pack_for = imod
+                .manifest
+                .content_pack_for
+                .iter()
+                .map(|framework| (framework.unique_id.trim(), EdgeKind::ContentPack));
+
+            for (target_id, kind) in dependencies.chain(content_pack_for) {
+                graph.edges.push(Edge {
+                    from,
+                    to: graph.index.get(&key(target_id)).copied(),
+                    target_id: target_id.to_owned(),
+                    kind,
+                });
+            }
+        }
+
+        graph
+    }
+
+    pub fn find(&self, id: &str) -> Option<usize> {
+        self.index.get(&key(id)).copied()
+    }
+
+    /// edges going out of `node`
+    pub fn dependencies(&self, node: usize) -> impl Iterator<Item = &Edge> {
+        self.edges.iter().filter(move |edge| edge.from == node)
+    }
+
+    /// edges pointing at `node`
+    pub fn dependents(&self, node: usize) -> impl Iterator<Item = &Edge> {
+        self.edges.iter().filter(move |edge| edge.to == Some(node))
+    }
+
+    /// every node that stops working without `node`, nearest first
+    pub fn required_by_transitive(&self, node: usize) -> Vec<usize> {
+        let mut seen = HashSet::from([node]);
+        let mut result = vec![];
+        let mut queue = VecDeque::from([node]);
+
+        while let Some(current) = queue.pop_front() {
+            for edge in self.dependents(current).filter(|edge| edge.required()) {
+                if seen.insert(edge.from) {
+                    result.push(edge.from);
+                    queue.push_back(edge.from);
+                }
+            }
+        }
+
+        result
+    }
+
+    pub fn unresolved(&self) -> impl Iterator<Item = &Edge> {
+        self.edges.iter().filter(|edge| edge.to.is_none())
+    }
+
+    /// dependencies before dependents, nodes stuck in a cycle are left out, see [`DependencyGraph::cycles`]
+    pub fn topological_order(&self) -> Vec<usize> {
+        let mut incoming = vec![0; self.nodes.len()];
+        for edge in &self.edges {
+            if edge.to.is_some() {
+                incoming[edge.from] += 1;
+            }
+        }
+
+        let mut queue: VecDeque<usize> = (0..self.nodes.len()).filter(|&node| incoming[node] == 0).collect();
+        let mut order = Vec::with_capacity(self.nodes.len());
+
+        while let Some(node) = queue.pop_front() {
+            order.push(node);
+            for edge in self.dependents(node) {
+                incoming[edge.from] -= 1;
+                if incoming[edge.from] == 0 {
+                    queue.push_back(edge.from);
+                }
+            }
+        }
+
+        order
+    }
+
+    /// strongly connected components with more than one mod, or a mod depending on itself
+    pub fn cycles(&self) -> Vec<Vec<usize>> {
+        struct Tarjan<'a> {
+            graph: &'a DependencyGraph,
+            counter: usize,
+            index: Vec<Option<usize>>,
+            lowlink: Vec<usize>,
+            on_stack: Vec<bool>,
+            stack: Vec<usize>,
+            components: Vec<Vec<usize>>,
+        }
+
+        impl Tarjan<'_> {
+            fn visit(&mut self, node: usize) {
+                self.index[node] = Some(self.counter);
+                self.lowlink[node] = self.counter;
+                self.counter += 1;
+                self.stack.push(node);
+                self.on_stack[node] = true;
+
+                let targets: Vec<usize> = self.graph.dependencies(node).filter_map(|edge| edge.to).collect();
+                for target in targets {
+                    match self.index[target] {
+                        None => {
+                            self.visit(target);
+                            self.lowlink[node] = self.lowlink[node].min(self.lowlink[target]);
+                        }
+                        Some(index) if self.on_stack[target] => {
+                            self.lowlink[node] = self.lowlink[node].min(index);
+                        }
+                        _ => {}
+                    }
+                }
+
+                if Some(self.lowlink[node]) == self.index[node] {
+                    let mut component = vec![];
+                    while let Some(member) = self.stack.pop() {
+                        self.on_stack[member] = false;
+                        component.push(member);
+                        if member == node {
+                            break;
+                        }
+                    }
+                    self.components.push(component);
+                }
+            }
+        }
+
+        let len = self.nodes.len();
+        let mut tarjan = Tarjan {
+            graph: self,
+            counter: 0,
+            index: vec![None; len],
+            lowlink: vec![0; len],
+            on_stack: vec![false; len],
+            stack: vec![],
+            components: vec![],
+        };
+        for node in 0..len {
+            if tarjan.index[node].is_none() {
+                tarjan.visit(node);
+            }
+        }
+
+        tarjan
+            .components
+            .into_iter()
+            .filter(|component| {
+                component.len() > 1
+                    || self
+                        .dependencies(component[0])
+                        .any(|edge| edge.to == Some(component[0]))
+            })
+            .collect()
+    }
+
+    pub fn to_dot(&self) -> String {
+        let escape = |s: &str| s.replace('\\', "\\\\").replace('"', "\\\"");
+        let mut out = String::from("digraph mods {\n    rankdir=LR;\n");
+
+        for node in &self.nodes {
+            let style = if node.active { "" } else { ", style=dashed" };
+            let _ = writeln!(
+                out,
+                "    \"{}\" [label=\"{}\"{style}];",
+                escape(&node.id),
+                escape(&node.name)
+            );
+        }
+        // in the order they're first needed so the output doesn't change between runs
+        let mut missing = HashSet::new();
+        for id in self
+            .unresolved()
+            .map(|edge| &edge.target_id)
+            .filter(|id| missing.insert(*id))
+        {
+            let _ = writeln!(out, "    \"{}\" [label=\"{0} (missing)\", color=red];", escape(id));
+        }
+        for edge in &self.edges {
+            let style = match edge.kind {
+                EdgeKind::Required => "",
+                EdgeKind::Optional => " [style=dashed]",
+                EdgeKind::ContentPack => " [style=bold]",
+            };
+            let _ = writeln!(
+                out,
+                "    \"{}\" -> \"{}\"{style};",
+                escape(&self.nodes[edge.from].id),
+                escape(&edge.target_id)
+            );
+        }
+
+        out.push_str("}\n");
+        out
+    }
+
+    pub fn to_mermaid(&self) -> String {
+        let escape = |s: &str| s.replace('"', "#quot;");
+        let mut out = String::from("graph LR\n");
+
+        for (i, node) in self.nodes.iter().enumerate() {
+            let _ = writeln!(out, "    n{i}[\"{}\"]", escape(&node.name));
+        }
+
+        let mut missing: Vec<&str> = vec![];
+        for edge in &self.edges {
+            let target = match edge.to {
+                Some(to) => format!("n{to}"),
+                None => {
+                    let position = missing.iter().position(|id| *id == edge.target_id).unwrap_or_else(|| {
+                        missing.push(&edge.target_id);
+                        missing.len() - 1
+                    });
+                    format!("m{position}")
+                }
+            };
+            let arrow = match edge.kind {
+                EdgeKind::Required => "-->",
+                EdgeKind::Optional => "-.->",
+                EdgeKind::ContentPack => "==>",
+            };
+            let _ = writeln!(out, "    n{} {arrow} {target}", edge.from);
+        }
+        for (i, id) in missing.iter().enumerate() {
+            let _ = writeln!(out, "    m{i}[\"{} (missing)\"]", escape(id));
+        }
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::PathBuf, time::SystemTime};
+
+    use serde_json::json;
+
+    use super::*;
+
+    /// `dependencies` are `(id, required)`
+    fn installed(id: &str, dependencies: &[(&str, bool)], content_pack_for: Option<&str>) -> InstalledMod {
+        let dependencies: Vec<_> = dependencies
+            .iter()
+            .map(|(id, required)| json!({"UniqueID": id, "IsRequired": required}))
+            .collect();
+        let manifest = json!({
+            "Name": format!("{id} name"),
+            "Author": "someone",
+            "Version": "1.0.0",
+            "UniqueID": id,
+            "Dependencies": dependencies,
+            "ContentPackFor": content_pack_for.map(|id| json!({"UniqueID": id})),
+        });
+        InstalledMod {
+            path: PathBuf::from(id),
+            active: true,
+            modified: SystemTime::UNIX_EPOCH,
+            manifest: serde_json::from_value(manifest).unwrap(),
+            group: None,
+            diagnostics: vec![],
+        }
+    }
+
+    fn ids(graph: &DependencyGraph, nodes: &[usize]) -> Vec<String> {
+        nodes.iter().map(|&node| graph.nodes[node].id.clone()).collect()
+    }
+
+    #[test]
+    fn resolves_edges_case_insensitively() {
+        let graph = DependencyGraph::new(&[
+            installed(
+                "Pack",
+                &[("pathoschild.ContentPatcher", true), ("Missing.Mod", false)],
+                None,
+            ),
+            installed("Pathoschild.ContentPatcher", &[], None),
+        ]);
+
+        assert_eq!(graph.edges.len(), 2);
+        assert_eq!(graph.edges[0].to, graph.find("PATHOSCHILD.CONTENTPATCHER"));
+        assert_eq!(graph.edges[1].to, None);
+        assert!(!graph.edges[1].required());
+        assert_eq!(graph.unresolved().count(), 1);
+    }
+
+    #[test]
+    fn orders_dependencies_before_dependents() {
+        let graph = DependencyGraph::new(&[
+            installed("C", &[("B", true)], None),
+            installed("B", &[("A", true), ("Missing", true)], None),
+            installed("D", &[("A", false)], Some("C")),
+            installed("A", &[], None),
+        ]);
+
+        assert_eq!(ids(&graph, &graph.topological_order()), ["A", "B", "C", "D"]);
+        assert_eq!(
+            ids(&graph, &graph.required_by_transitive(graph.find("a").unwrap())),
+            ["B", "C", "D"]
+        );
+        assert!(graph.cycles().is_empty());
+    }
+
+    #[test]
+    fn finds_cycles_and_leaves_them_out_of_the_order() {
+        let graph = DependencyGraph::new(&[
+            installed("A", &[("B", true)], None),
+            installed("B", &[("C", false)], None),
+            installed("C", &[("A", true)], None),
+            installed("Self", &[("self", true)], None),
+            installed("Free", &[], None),
+            installed("After", &[("A", true)], None),
+        ]);
+
+        let mut cycles: Vec<Vec<String>> = graph
+            .cycles()
+            .iter()
+            .map(|cycle| {
+                let mut ids = ids(&graph, cycle);
+                ids.sort();
+                ids
+            })
+            .collect();
+        cycles.sort();
+        assert_eq!(cycles, [vec!["A", "B", "C"], vec!["Self"]]);
+        assert_eq!(ids(&graph, &graph.topological_order()), ["Free"]);
+    }
+
+    #[test]
+    fn exports_dot_and_mermaid() {
+        let graph = DependencyGraph::new(&[
+            installed(
+                "Pack",
+                &[("Lib", true), ("Gone", true), ("Also.Gone", false)],
+                Some("Framework"),
+            ),
+            installed("Lib", &[("Gone", false)], None),
+        ]);
+
+        assert_eq!(
+            graph.to_dot(),
+            r#"digraph mods {
+    rankdir=LR;
+    "Pack" [label="Pack name"];
+    "Lib" [label="Lib name"];
+    "Gone" [label="Gone (missing)", color=red];
+    "Also.Gone" [label="Also.Gone (missing)", color=red];
+    "Framework" [label="Framework (missing)", color=red];
+    "Pack" -> "Lib";
+    "Pack" -> "Gone";
+    "Pack" -> "Also.Gone" [style=dashed];
+    "Pack" -> "Framework" [style=bold];
+    "Lib" -> "Gone" [style=dashed];
+}
+"#
+        );
+        assert_eq!(
+            graph.to_mermaid(),
+            r#"graph LR
+    n0["Pack name"]
+    n1["Lib name"]
+    n0 --> n1
+    n0 --> m0
+    n0 -.-> m1
+    n0 ==> m2
+    n1 -.-> m0
+    m0["Gone (missing)"]
+    m1["Also.Gone (missing)"]
+    m2["Framework (missing)"]
+"#
+        );
+    }
+}
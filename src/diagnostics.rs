@@ -0,0 +1,342 @@
+use std::{fs::read_to_string, path::Path};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::{i18n::tr, ModManifest};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Severity {
+    /// smapi loads the mod anyway, but something about it is off
+    Warning,
+    /// the mod couldn't be loaded at all
+    Error,
+}
+
+/// something wrong with a mod folder that didn't stop the rest of the scan
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn error(message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            message: message.into(),
+        }
+    }
+
+    pub fn warning(message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            message: message.into(),
+        }
+    }
+}
+
+const MANIFEST_FIELDS: &[&str] = &[
+    "Name",
+    "Author",
+    "Version",
+    "Description",
+    "UniqueID",
+    "EntryDll",
+    "MinimumApiVersion",
+    "Dependencies",
+    "UpdateKeys",
+    "ContentPackFor",
+];
+
+const DEPENDENCY_FIELDS: &[&str] = &["UniqueID", "MinimumVersion", "IsRequired"];
+
+/// smapi reads json case insensitively, renames keys like `uniqueId` to their proper spelling in `fields`
+fn normalize_keys(
+    object: Map<String, Value>,
+    fields: &[&str],
+    context: &str,
+    warnings: &mut Vec<Diagnostic>,
+) -> Map<String, Value> {
+    object
+        .into_iter()
+        .map(
+            |(key, value)| match fields.iter().find(|field| field.eq_ignore_ascii_case(&key)) {
+                Some(field) if *field != key => {
+                    warnings.push(Diagnostic::warning(tr!(
+                        "{}{} should be spelled {}",
+                        context,
+                        key,
+                        field
+                    )));
+                    (field.to_string(), value)
+                }
+                _ => (key, value),
+            },
+        )
+        .collect()
+}
+
+/// turns numbers like `1.2` and old smapi objects like `{"MajorVersion": 1, ...}` into version strings
+fn normalize_version(object: &mut Map<String, Value>, field: &str, context: &str, warnings: &mut Vec<Diagnostic>) {
+    let version = match object.get(field) {
+        Some(Value::Number(number)) => number.to_string(),
+        Some(Value::Object(parts)) => {
+            let part = |name: &str| {
+                parts
+                    .iter()
+                    .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                    .map(|(_, value)| value.to_string().trim_matches('"').to_owned())
+            };
+            let mut version = ["MajorVersion", "MinorVersion", "PatchVersion"]
+                .map(|name| part(name).unwrap_or_else(|| "0".to_owned()))
+                .join(".");
+            if let Some(build) = part("Build").filter(|build| build != "null") {
+                version = format!("{version}-{build}");
+            }
+            version
+        }
+        _ => return,
+    };
+    warnings.push(Diagnostic::warning(tr!(
+        "{}{} should be a string like \"{}\"",
+        context,
+        field,
+        version
+    )));
+    object.insert(field.to_owned(), Value::String(version));
+}
+
+fn is_missing(object: &Map<String, Value>, field: &str) -> bool {
+    match object.get(field) {
+        None | Some(Value::Null) => true,
+        Some(Value::String(value)) => value.trim().is_empty(),
+        _ => false,
+    }
+}
+
+/// fixes up everything smapi would accept but the `ModManifest` struct doesn't, each fix becomes a warning
+fn normalize_manifest(object: Map<String, Value>, warnings: &mut Vec<Diagnostic>) -> Result<Value, Diagnostic> {
+    let mut object = normalize_keys(object, MANIFEST_FIELDS, "", warnings);
+
+    normalize_version(&mut object, "Version", "", warnings);
+    for field in ["Name", "Version", "UniqueID"] {
+        if is_missing(&object, field) {
+            return Err(Diagnostic::error(tr!("manifest.json has no {}", field)));
+        }
+    }
+    if is_missing(&object, "Author") {
+        warnings.push(Diagnostic::warning(tr!("manifest.json has no Author")));
+        object.insert("Author".to_owned(), Value::String(String::new()));
+    }
+    if let Some(Value::String(key)) = object.get("UpdateKeys") {
+        warnings.push(Diagnostic::warning(tr!("UpdateKeys should be a list")));
+        object.insert("UpdateKeys".to_owned(), Value::Array(vec![Value::String(key.clone())]));
+    }
+
+    if let Some(Value::Array(dependencies)) = object.remove("Dependencies") {
+        let mut normalized = vec![];
+        for dependency in dependencies {
+            let Value::Object(dependency) = dependency else {
+                warnings.push(Diagnostic::warning(tr!("Ignoring dependency {}", dependency)));
+                continue;
+            };
+            let mut dependency = normalize_keys(dependency, DEPENDENCY_FIELDS, "Dependencies: ", warnings);
+            normalize_version(&mut dependency, "MinimumVersion", "Dependencies: ", warnings);
+            if is_missing(&dependency, "UniqueID") {
+                warnings.push(Diagnostic::warning(tr!("Ignoring a dependency without UniqueID")));
+                continue;
+            }
+            normalized.push(Value::Object(dependency));
+        }
+        object.insert("Dependencies".to_owned(), Value::Array(normalized));
+    }
+
+    if let Some(Value::Object(content_pack_for)) = object.remove("ContentPackFor") {
+        let mut content_pack_for = normalize_keys(content_pack_for, DEPENDENCY_FIELDS, "ContentPackFor: ", warnings);
+        normalize_version(&mut content_pack_for, "MinimumVersion", "ContentPackFor: ", warnings);
+        object.insert("ContentPackFor".to_owned(), Value::Object(content_pack_for));
+    }
+
+    Ok(Value::Object(object))
+}
+
+/// pest errors span several lines with an ascii art pointer, the `= expected ...` line is the useful part
+fn describe_json5_error(err: json5::Error) -> String {
+    let json5::Error::Message { msg, location } = err;
+    let msg = msg
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("= "))
+        .unwrap_or_else(|| msg.lines().next().unwrap_or_default())
+        .to_owned();
+    match location {
+        Some(location) => format!("line {} column {}: {msg}", location.line, location.column),
+        None => msg,
+    }
+}
+
+/// parses a manifest as leniently as smapi does, returns the manifest with warnings about everything that had to be
+/// fixed up
+pub fn parse_manifest(content: &str) -> Result<(ModManifest, Vec<Diagnostic>), Diagnostic> {
+    let mut warnings = vec![];
+    let content = match content.strip_prefix('\u{feff}') {
+        Some(content) => {
+            warnings.push(Diagnostic::warning(tr!("manifest.json starts with a byte order mark")));
+            content
+        }
+        None => content,
+    };
+
+    let value: Value = json5::from_str(content)
+        .map_err(|err| Diagnostic::error(tr!("Invalid manifest.json, {}", describe_json5_error(err))))?;
+    let Value::Object(object) = value else {
+        return Err(Diagnostic::error(tr!("manifest.json isn't a json object")));
+    };
+    let manifest = serde_json::from_value(normalize_manifest(object, &mut warnings)?)
+        .map_err(|err| Diagnostic::error(tr!("Invalid manifest.json, {}", err)))?;
+
+    Ok((manifest, warnings))
+}
+
+pub fn read_manifest(path: &Path) -> Result<(ModManifest, Vec<Diagnostic>), Diagnostic> {
+    let content = read_to_string(path).map_err(|err| Diagnostic::error(tr!("Can't read manifest.json: {}", err)))?;
+    parse_manifest(&content)
+}
+
+/// stands in for the manifest of a mod that failed to load so it still shows up in the lists, `folder` is the path
+/// of the mod relative to where it was found
+pub fn placeholder_manifest(folder: &str) -> ModManifest {
+    let name = Path::new(folder)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| folder.to_owned());
+    ModManifest {
+        name,
+        // unique ids can't contain colons, so this never clashes with a real mod
+        unique_id: format!("{PLACEHOLDER_PREFIX}{folder}"),
+        ..Default::default()
+    }
+}
+
+const PLACEHOLDER_PREFIX: &str = "broken:";
+
+/// whether `id` belongs to a [placeholder_manifest] rather than a real mod
+pub fn is_placeholder(id: &str) -> bool {
+    id.starts_with(PLACEHOLDER_PREFIX)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn normalize(manifest: Value) -> (Result<Value, Diagnostic>, Vec<Diagnostic>) {
+        let Value::Object(object) = manifest else {
+            panic!("not an object")
+        };
+        let mut warnings = vec![];
+        (normalize_manifest(object, &mut warnings), warnings)
+    }
+
+    #[test]
+    fn strips_byte_order_mark() {
+        let content =
+            "\u{feff}{\"Name\": \"Mod\", \"Author\": \"Me\", \"Version\": \"1.0.0\", \"UniqueID\": \"Me.Mod\"}";
+        let (manifest, warnings) = parse_manifest(content).unwrap();
+        assert_eq!(manifest.unique_id, "Me.Mod");
+        assert_eq!(
+            warnings,
+            [Diagnostic::warning("manifest.json starts with a byte order mark")]
+        );
+    }
+
+    #[test]
+    fn turns_numbers_and_objects_into_version_strings() {
+        let (manifest, warnings) = normalize(json!({
+            "Name": "Mod",
+            "Author": "Me",
+            "Version": 1.5,
+            "UniqueID": "Me.Mod",
+            "Dependencies": [
+                {"UniqueID": "Other.Mod", "MinimumVersion": {"MajorVersion": 2, "MinorVersion": 1}},
+                {"UniqueID": "Beta.Mod", "MinimumVersion": {"MajorVersion": 1, "MinorVersion": 0, "PatchVersion": 3, "Build": "beta"}},
+            ],
+        }));
+        let manifest = manifest.unwrap();
+        assert_eq!(manifest["Version"], "1.5");
+        assert_eq!(manifest["Dependencies"][0]["MinimumVersion"], "2.1.0");
+        assert_eq!(manifest["Dependencies"][1]["MinimumVersion"], "1.0.3-beta");
+        assert_eq!(warnings.len(), 3);
+    }
+
+    #[test]
+    fn fixes_key_casing() {
+        let (manifest, warnings) = normalize(json!({
+            "name": "Mod",
+            "Author": "Me",
+            "Version": "1.0.0",
+            "UniqueId": "Me.Mod",
+            "ContentPackFor": {"uniqueID": "Pathoschild.ContentPatcher"},
+        }));
+        let manifest = manifest.unwrap();
+        assert_eq!(manifest["Name"], "Mod");
+        assert_eq!(manifest["UniqueID"], "Me.Mod");
+        assert_eq!(manifest["ContentPackFor"]["UniqueID"], "Pathoschild.ContentPatcher");
+        assert!(manifest.get("UniqueId").is_none());
+        assert_eq!(
+            warnings,
+            [
+                Diagnostic::warning("UniqueId should be spelled UniqueID"),
+                Diagnostic::warning("name should be spelled Name"),
+                Diagnostic::warning("ContentPackFor: uniqueID should be spelled UniqueID"),
+            ]
+        );
+    }
+
+    #[test]
+    fn wraps_a_single_update_key() {
+        let (manifest, warnings) = normalize(json!({
+            "Name": "Mod",
+            "Author": "Me",
+            "Version": "1.0.0",
+            "UniqueID": "Me.Mod",
+            "UpdateKeys": "Nexus:1234",
+        }));
+        assert_eq!(manifest.unwrap()["UpdateKeys"], json!(["Nexus:1234"]));
+        assert_eq!(warnings, [Diagnostic::warning("UpdateKeys should be a list")]);
+    }
+
+    #[test]
+    fn missing_author_is_only_a_warning() {
+        let (manifest, warnings) = normalize(json!({"Name": "Mod", "Version": "1.0.0", "UniqueID": "Me.Mod"}));
+        assert_eq!(manifest.unwrap()["Author"], "");
+        assert_eq!(warnings, [Diagnostic::warning("manifest.json has no Author")]);
+
+        let (manifest, _) = normalize(json!({"Name": "Mod", "Author": "Me", "Version": "1.0.0", "UniqueID": " "}));
+        assert_eq!(
+            manifest.unwrap_err(),
+            Diagnostic::error("manifest.json has no UniqueID")
+        );
+    }
+
+    #[test]
+    fn skips_dependencies_without_unique_id() {
+        let (manifest, warnings) = normalize(json!({
+            "Name": "Mod",
+            "Author": "Me",
+            "Version": "1.0.0",
+            "UniqueID": "Me.Mod",
+            "Dependencies": [{"MinimumVersion": "1.0.0"}, "Other.Mod", {"uniqueid": "Other.Mod"}],
+        }));
+        assert_eq!(manifest.unwrap()["Dependencies"], json!([{"UniqueID": "Other.Mod"}]));
+        assert_eq!(
+            warnings,
+            [
+                Diagnostic::warning("Ignoring a dependency without UniqueID"),
+                Diagnostic::warning("Ignoring dependency \"Other.Mod\""),
+                Diagnostic::warning("Dependencies: uniqueid should be spelled UniqueID"),
+            ]
+        );
+    }
+}
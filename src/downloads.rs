@@ -0,0 +1,539 @@
+use std::{
+    fs::File,
+    io,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use color_eyre::{eyre::eyre, Result};
+use log::{debug, info};
+use once_cell::sync::{Lazy, OnceCell};
+use percent_encoding::percent_decode_str;
+use reqwest::{header, StatusCode, Url};
+use sha2::{Digest, Sha256};
+use tokio::{fs::OpenOptions, io::AsyncWriteExt};
+
+use crate::{config::SVMMConfig, i18n::tr, nexusapi::NexusClient, DownloadTask};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DownloadStatus {
+    Resolving,
+    Downloading,
+    Verifying,
+    Done(PathBuf),
+    Failed(String),
+    Cancelled,
+}
+
+#[derive(Clone, Debug)]
+pub struct Download {
+    pub id: u64,
+    /// the `nxm://` or http link the user gave us, kept around for retries
+    pub link: String,
+    pub name: String,
+    pub downloaded: u64,
+    pub total: Option<u64>,
+    pub status: DownloadStatus,
+    cancel: Arc<AtomicBool>,
+    /// the `.part` file this download writes to, only one running download may own it
+    part_path: Option<PathBuf>,
+}
+
+/// a resolved download, `link` may have been a `nxm://` link which doesn't point at a file yet
+#[derive(Clone, Debug)]
+pub struct DownloadRequest {
+    pub url: Url,
+    pub file_name: String,
+    pub expected_sha256: Option<String>,
+}
+
+type Listener = Mutex<Box<dyn Fn(Vec<Download>) + Send>>;
+
+static DOWNLOADS: Lazy<Mutex<Vec<Download>>> = Lazy::new(Default::default);
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+static LISTENER: OnceCell<Listener> = OnceCell::new();
+
+static CLIENT: Lazy<reqwest::Client> = Lazy::new(|| {
+    reqwest::Client::builder()
+        .user_agent("StardewValleyModManager (https://github.com/tricked/stardew-valley-mod-manager)")
+        .connect_timeout(Duration::from_secs(15))
+        .build()
+        .unwrap()
+});
+
+/// called with a snapshot of every download whenever one of them changes
+pub fn set_listener(listener: impl Fn(Vec<Download>) + Send + 'static) {
+    if LISTENER.set(Mutex::new(Box::new(listener))).is_err() {
+        debug!("Download listener was already set");
+    }
+}
+
+pub fn is_download_link(link: &str) -> bool {
+    let link = link.trim().to_ascii_lowercase();
+    link.starts_with("nxm://") || link.starts_with("http://") || link.starts_with("https://")
+}
+
+fn notify() {
+    if let Some(listener) = LISTENER.get() {
+        let downloads = DOWNLOADS.lock().unwrap().clone();
+        listener.lock().unwrap()(downloads);
+    }
+}
+
+fn update(id: u64, f: impl FnOnce(&mut Download)) {
+    if let Some(download) = DOWNLOADS.lock().unwrap().iter_mut().find(|d| d.id == id) {
+        f(download);
+    }
+    notify();
+}
+
+fn is_running(download: &Download) -> bool {
+    matches!(
+        download.status,
+        DownloadStatus::Resolving | DownloadStatus::Downloading | DownloadStatus::Verifying
+    )
+}
+
+/// names the download and takes `part_path` for it, fails if another running download already writes there
+fn claim(id: u64, name: &str, part_path: &Path) -> Result<()> {
+    let mut downloads = DOWNLOADS.lock().unwrap();
+    if downloads
+        .iter()
+        .any(|d| d.id != id && is_running(d) && d.part_path.as_deref() == Some(part_path))
+    {
+        return Err(eyre!(tr!("{} is already being downloaded", name)));
+    }
+    if let Some(download) = downloads.iter_mut().find(|d| d.id == id) {
+        download.name = name.to_owned();
+        download.part_path = Some(part_path.to_owned());
+    }
+    drop(downloads);
+    notify();
+    Ok(())
+}
+
+pub fn cancel(id: u64) {
+    if let Some(download) = DOWNLOADS.lock().unwrap().iter().find(|d| d.id == id) {
+        download.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+/// removes a download from the list, returning its link so it can be retried
+pub fn forget(id: u64) -> Option<String> {
+    let mut downloads = DOWNLOADS.lock().unwrap();
+    let position = downloads.iter().position(|d| d.id == id)?;
+    let download = downloads.remove(position);
+    drop(downloads);
+    notify();
+    Some(download.link)
+}
+
+pub fn clear_finished() {
+    DOWNLOADS.lock().unwrap().retain(is_running);
+    notify();
+}
+
+/// the last component of a name we got from a server, never let a server pick where we write to
+fn safe_file_name(name: &str) -> Option<String> {
+    let name = Path::new(name).file_name()?.to_string_lossy().to_string();
+    (!name.is_empty()).then_some(name)
+}
+
+fn file_name_from_url(url: &Url) -> Option<String> {
+    let segment = url.path_segments()?.next_back()?;
+    safe_file_name(&percent_decode_str(segment).decode_utf8_lossy())
+}
+
+/// nxm links look like `nxm://stardewvalley/mods/<mod>/files/<file>?key=..&expires=..`
+async fn resolve_nxm(link: &Url, config: &SVMMConfig) -> Result<DownloadRequest> {
+    let client = NexusClient::from_config(config).ok_or(eyre!(tr!("A nexus api key is required for nxm:// links")))?;
+
+    let segments: Vec<_> = link.path_segments().map(|s| s.collect()).unwrap_or_default();
+    let (mod_id, file_id) = match segments.as_slice() {
+        ["mods", mod_id, "files", file_id, ..] => (mod_id.parse::<u32>()?, file_id.parse::<u64>()?),
+        _ => return Err(eyre!(tr!("Unsupported nxm link: {}", link))),
+    };
+
+    let query = |name: &str| {
+        link.query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.to_string())
+    };
+
+    let url = client
+        .get_download_link(mod_id, file_id, query("key"), query("expires"))
+        .await?;
+    let file_name = client
+        .get_files(mod_id)
+        .await?
+        .into_iter()
+        .find(|file| file.file_id == file_id)
+        .and_then(|file| safe_file_name(&file.file_name))
+        .or_else(|| file_name_from_url(&url))
+        .unwrap_or_else(|| format!("{mod_id}-{file_id}.zip"));
+
+    Ok(DownloadRequest {
+        url,
+        file_name,
+        expected_sha256: None,
+    })
+}
+
+/// plain http links may carry a checksum as `#sha256=<hex>`
+pub async fn resolve(link: &str, config: &SVMMConfig) -> Result<DownloadRequest> {
+    let url = Url::parse(link.trim())?;
+
+    if url.scheme() == "nxm" {
+        return resolve_nxm(&url, config).await;
+    }
+
+    let expected_sha256 = url
+        .fragment()
+        .and_then(|fragment| fragment.strip_prefix("sha256="))
+        .map(|hash| hash.to_ascii_lowercase());
+    let mut url = url.clone();
+    url.set_fragment(None);
+
+    let file_name = file_name_from_url(&url).unwrap_or_else(|| "download.zip".to_owned());
+
+    Ok(DownloadRequest {
+        url,
+        file_name,
+        expected_sha256,
+    })
+}
+
+pub fn sha256_file(path: &Path) -> Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// resolves once `cancel` is set, a stalled server shouldn't keep a download from being cancelled
+async fn cancelled(cancel: &AtomicBool) {
+    while !cancel.load(Ordering::Relaxed) {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+}
+
+/// appends the response body to `part_path`, returning the size of the part file afterwards
+async fn write_body(
+    id: u64,
+    mut response: reqwest::Response,
+    part_path: &Path,
+    offset: u64,
+    cancel: &AtomicBool,
+) -> Result<u64> {
+    let total = response.content_length().map(|len| len + offset);
+    update(id, |d| {
+        d.status = DownloadStatus::Downloading;
+        d.downloaded = offset;
+        d.total = total;
+    });
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(offset > 0)
+        .truncate(offset == 0)
+        .open(part_path)
+        .await?;
+
+    let mut last_update = Instant::now();
+    let mut downloaded = offset;
+    loop {
+        // the part file is kept so a retry resumes where this stopped
+        let chunk = tokio::select! {
+            chunk = response.chunk() => chunk?,
+            _ = cancelled(cancel) => {
+                file.flush().await?;
+                return Err(eyre!(tr!("Download cancelled")));
+            }
+        };
+        let Some(chunk) = chunk else {
+            break;
+        };
+        file.write_all(&chunk).await?;
+        downloaded += chunk.len() as u64;
+
+        // dont flood the ui with updates
+        if last_update.elapsed() > Duration::from_millis(100) {
+            last_update = Instant::now();
+            update(id, |d| d.downloaded = downloaded);
+        }
+    }
+    file.flush().await?;
+
+    if let Some(total) = total {
+        if downloaded < total {
+            return Err(eyre!(tr!(
+                "Download ended early ({}/{} bytes), retry to resume",
+                downloaded,
+                total
+            )));
+        }
+    }
+
+    Ok(downloaded)
+}
+
+/// downloads into `<staging>/<name>.part`, resuming a previous partial download if the server allows it
+async fn fetch(id: u64, request: &DownloadRequest, staging: &Path, cancel: &AtomicBool) -> Result<PathBuf> {
+    let part_path = staging.join(format!("{}.part", request.file_name));
+    claim(id, &request.file_name, &part_path)?;
+    let target_path = staging.join(&request.file_name);
+
+    let offset = tokio::fs::metadata(&part_path).await.map(|m| m.len()).unwrap_or(0);
+
+    let mut builder = CLIENT.get(request.url.clone());
+    if offset > 0 {
+        debug!("Resuming {} at {offset} bytes", request.file_name);
+        builder = builder.header(header::RANGE, format!("bytes={offset}-"));
+    }
+    let response = builder.send().await?;
+
+    let downloaded = match response.status() {
+        StatusCode::PARTIAL_CONTENT => write_body(id, response, &part_path, offset, cancel).await?,
+        // the part file most likely already holds everything
+        StatusCode::RANGE_NOT_SATISFIABLE if offset > 0 => {
+            let head = CLIENT.head(request.url.clone()).send().await?.error_for_status()?;
+            if head.content_length() != Some(offset) {
+                tokio::fs::remove_file(&part_path).await?;
+                return Err(eyre!(tr!(
+                    "Partial download of {} is corrupt, retry it",
+                    request.file_name
+                )));
+            }
+            offset
+        }
+        // server ignored the range header, start over
+        status if status.is_success() => write_body(id, response, &part_path, 0, cancel).await?,
+        status => return Err(eyre!(tr!("Server responded with {} for {}", status, request.file_name))),
+    };
+
+    update(id, |d| {
+        d.downloaded = downloaded;
+        d.status = DownloadStatus::Verifying;
+    });
+
+    let hash_path = part_path.clone();
+    let hash = tokio::task::spawn_blocking(move || sha256_file(&hash_path)).await??;
+    info!("Downloaded {} sha256: {hash}", request.file_name);
+
+    if let Some(expected) = &request.expected_sha256 {
+        if &hash != expected {
+            tokio::fs::remove_file(&part_path).await?;
+            return Err(eyre!(tr!(
+                "Checksum mismatch for {}: expected {} got {}",
+                request.file_name,
+                expected,
+                hash
+            )));
+        }
+    }
+
+    tokio::fs::rename(&part_path, &target_path).await?;
+
+    Ok(target_path)
+}
+
+/// resolves and downloads `link` into the staging directory, tracking progress in the download list
+pub async fn start(link: String, config: &SVMMConfig, staging: &Path) -> Result<PathBuf> {
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    let cancel = Arc::new(AtomicBool::new(false));
+
+    DOWNLOADS.lock().unwrap().push(Download {
+        id,
+        name: link.clone(),
+        link: link.clone(),
+        downloaded: 0,
+        total: None,
+        status: DownloadStatus::Resolving,
+        cancel: cancel.clone(),
+        part_path: None,
+    });
+    notify();
+
+    let result = async {
+        let request = resolve(&link, config).await?;
+        tokio::fs::create_dir_all(staging).await?;
+        fetch(id, &request, staging, &cancel).await
+    }
+    .await;
+
+    match &result {
+        Ok(path) => update(id, |d| d.status = DownloadStatus::Done(path.clone())),
+        Err(_) if cancel.load(Ordering::Relaxed) => update(id, |d| d.status = DownloadStatus::Cancelled),
+        Err(err) => update(id, |d| d.status = DownloadStatus::Failed(err.to_string())),
+    }
+
+    result
+}
+
+fn format_size(bytes: u64) -> String {
+    format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+}
+
+impl From<&Download> for DownloadTask {
+    fn from(download: &Download) -> Self {
+        let progress = match (download.total, &download.status) {
+            (_, DownloadStatus::Done(_)) => 1.0,
+            (Some(total), _) if total > 0 => download.downloaded as f32 / total as f32,
+            _ => 0.0,
+        };
+
+        let size = match download.total {
+            Some(total) => format!("{} / {}", format_size(download.downloaded), format_size(total)),
+            None => format_size(download.downloaded),
+        };
+
+        let status = match &download.status {
+            DownloadStatus::Resolving => tr!("Resolving link"),
+            DownloadStatus::Downloading => size,
+            DownloadStatus::Verifying => tr!("Verifying checksum"),
+            DownloadStatus::Done(_) => tr!("Done"),
+            DownloadStatus::Failed(err) => tr!("Failed: {}", err),
+            DownloadStatus::Cancelled => tr!("Cancelled"),
+        };
+
+        DownloadTask {
+            id: download.id as i32,
+            name: download.name.clone().into(),
+            status: status.into(),
+            progress,
+            running: is_running(download),
+            failed: matches!(download.status, DownloadStatus::Failed(_) | DownloadStatus::Cancelled),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::{serve, Response};
+
+    const BODY: &[u8] = b"stardew valley mod";
+
+    fn sha256(bytes: &[u8]) -> String {
+        format!("{:x}", Sha256::digest(bytes))
+    }
+
+    /// serves `BODY` at every path, answering range requests like a cdn does
+    async fn file_server() -> String {
+        serve(|request| {
+            let start = request
+                .header("range")
+                .and_then(|range| range.strip_prefix("bytes="))
+                .and_then(|range| range.strip_suffix('-'))
+                .and_then(|start| start.parse::<usize>().ok());
+            match start {
+                Some(start) if start >= BODY.len() => Response::new(416, ""),
+                Some(start) => Response::new(206, &BODY[start..]).header(
+                    "Content-Range",
+                    &format!("bytes {start}-{}/{}", BODY.len() - 1, BODY.len()),
+                ),
+                None => Response::new(200, BODY),
+            }
+        })
+        .await
+    }
+
+    fn download_of(link: &str) -> Option<Download> {
+        DOWNLOADS.lock().unwrap().iter().find(|d| d.link == link).cloned()
+    }
+
+    async fn wait_for(link: &str, done: impl Fn(&Download) -> bool) -> Download {
+        for _ in 0..100 {
+            if let Some(download) = download_of(link).filter(|d| done(d)) {
+                return download;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        panic!("{link} never got there: {:?}", download_of(link));
+    }
+
+    #[tokio::test]
+    async fn resumes_from_the_part_file() {
+        let url = file_server().await;
+        let staging = tempfile::tempdir().unwrap();
+        std::fs::write(staging.path().join("resume.zip.part"), &BODY[..7]).unwrap();
+
+        let link = format!("{url}/files/resume.zip#sha256={}", sha256(BODY));
+        let path = start(link, &SVMMConfig::default(), staging.path()).await.unwrap();
+        assert_eq!(path, staging.path().join("resume.zip"));
+        assert_eq!(std::fs::read(&path).unwrap(), BODY);
+        assert!(!staging.path().join("resume.zip.part").exists());
+    }
+
+    #[tokio::test]
+    async fn checksum_mismatch_discards_the_download() {
+        let url = file_server().await;
+        let staging = tempfile::tempdir().unwrap();
+
+        let link = format!("{url}/files/bad.zip#sha256={}", sha256(b"something else"));
+        let err = start(link, &SVMMConfig::default(), staging.path()).await.unwrap_err();
+        assert!(err.to_string().contains("bad.zip"), "{err}");
+        assert!(!staging.path().join("bad.zip").exists());
+        assert!(!staging.path().join("bad.zip.part").exists());
+    }
+
+    #[tokio::test]
+    async fn cancel_keeps_the_part_file_and_one_download_owns_it() {
+        let url = serve(|_| Response::new(200, BODY).stall_after(5)).await;
+        let staging = tempfile::tempdir().unwrap();
+        let link = format!("{url}/files/stalled.zip");
+
+        let first = tokio::spawn({
+            let (link, staging) = (link.clone(), staging.path().to_owned());
+            async move { start(link, &SVMMConfig::default(), &staging).await }
+        });
+        let running = wait_for(&link, |d| d.status == DownloadStatus::Downloading).await;
+
+        // the same file from another link would write into the same part file
+        let second = start(format!("{link}?again"), &SVMMConfig::default(), staging.path()).await;
+        assert!(second.unwrap_err().to_string().contains("already being downloaded"));
+
+        let part_path = staging.path().join("stalled.zip.part");
+        for _ in 0..100 {
+            if std::fs::metadata(&part_path).map(|m| m.len()).unwrap_or(0) == 5 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        cancel(running.id);
+        assert!(first.await.unwrap().is_err());
+        assert_eq!(download_of(&link).unwrap().status, DownloadStatus::Cancelled);
+        assert_eq!(std::fs::read(&part_path).unwrap(), &BODY[..5]);
+    }
+
+    #[tokio::test]
+    async fn nxm_file_names_stay_inside_the_staging_dir() {
+        let url = serve(|request| match request.path.split('?').next().unwrap() {
+            "/games/stardewvalley/mods/7/files/8/download_link.json" => Response::new(
+                200,
+                r#"[{"name":"CDN","short_name":"CDN","URI":"https://cdn.example/files/cdn.zip"}]"#,
+            ),
+            "/games/stardewvalley/mods/7/files.json" => Response::new(
+                200,
+                r#"{"files":[{"file_id":8,"file_name":"../../evil.zip"}],"file_updates":[]}"#,
+            ),
+            _ => Response::new(404, "{}"),
+        })
+        .await;
+        let config = SVMMConfig {
+            nexus_api_key: Some("secret".into()),
+            nexus_api_url: Some(url),
+            ..Default::default()
+        };
+
+        let request = resolve("nxm://stardewvalley/mods/7/files/8?key=a&expires=1", &config)
+            .await
+            .unwrap();
+        assert_eq!(request.file_name, "evil.zip");
+        assert_eq!(request.url.as_str(), "https://cdn.example/files/cdn.zip");
+    }
+}
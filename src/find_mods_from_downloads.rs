@@ -1,7 +1,10 @@
 //TODO: logging
 use color_eyre::Result;
+use log::warn;
 use serde::{Deserialize, Serialize};
+use slint::Model;
 use std::{
+    cmp::Ordering,
     fs::File,
     io::Read,
     path::{Path, PathBuf},
@@ -11,13 +14,21 @@ use time::{macros::format_description, OffsetDateTime};
 use walkdir::{DirEntry, WalkDir};
 use zip::read::ZipArchive;
 
-use crate::{mods_to_modelrc, InstalledMod, ModManifest, ModsZip};
+use crate::{
+    diagnostics::{parse_manifest, placeholder_manifest, read_manifest, Diagnostic},
+    generic_to_modelrc,
+    i18n::tr,
+    mods_to_modelrc, InstalledMod, ModManifest, ModsZip,
+};
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ZipMod {
     path: PathBuf,
     created_at: SystemTime,
     manifests: Vec<ZipModMod>,
+    /// why the archive couldn't be read, it has no manifests then
+    #[serde(default)]
+    error: Option<String>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -25,11 +36,224 @@ pub struct ZipModMod {
     // relative path
     manifest_path: PathBuf,
     manifest: ModManifest,
+    #[serde(default)]
+    diagnostics: Vec<Diagnostic>,
+}
+/// where one mod of an archive ends up
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InstallTarget {
+    /// the mod root inside the archive, empty when the manifest is at the root
+    pub source: PathBuf,
+    /// the folder name in the mods folder
+    pub folder: String,
+    pub unique_id: String,
+}
+
+/// files archivers leave behind that no mod needs
+pub fn is_junk(path: &Path) -> bool {
+    let junk_file = path.file_name().is_some_and(|name| {
+        let name = name.to_string_lossy();
+        name.starts_with("._")
+            || ["Thumbs.db", ".DS_Store", "desktop.ini"]
+                .iter()
+                .any(|junk| name.eq_ignore_ascii_case(junk))
+    });
+    junk_file || path.components().any(|component| component.as_os_str() == "__MACOSX")
+}
+
+/// turns a mod name into something every filesystem accepts as a folder name
+fn sensible_folder_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_control() || r#"<>:"/\|?*"#.contains(c) {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect();
+    // windows doesn't like trailing dots and spaces
+    name.trim().trim_end_matches(['.', ' ']).to_owned()
+}
+
+impl ZipModMod {
+    /// broken manifests get a placeholder so the mod still shows up with its error
+    fn new(manifest_path: PathBuf, manifest: Result<(ModManifest, Vec<Diagnostic>), Diagnostic>) -> Self {
+        match manifest {
+            Ok((manifest, diagnostics)) => Self {
+                manifest_path,
+                manifest,
+                diagnostics,
+            },
+            Err(diagnostic) => {
+                let folder = manifest_path
+                    .parent()
+                    .unwrap_or(Path::new(""))
+                    .to_string_lossy()
+                    .to_string();
+                Self {
+                    manifest: placeholder_manifest(&folder),
+                    manifest_path,
+                    diagnostics: vec![diagnostic],
+                }
+            }
+        }
+    }
+
+    /// the folder of the mod inside the archive, empty when the manifest is at the root
+    pub fn dir(&self) -> &Path {
+        self.manifest_path.parent().unwrap_or(Path::new(""))
+    }
+}
+
+impl ZipMod {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub(crate) fn manifests(&self) -> impl Iterator<Item = &ModManifest> {
+        self.manifests.iter().map(|zip_mod| &zip_mod.manifest)
+    }
+
+    /// one folder per mod root. Manifests inside another mod belong to that mod, folders are named after the mod root
+    /// in the archive or after the mod when its manifest is at the root of the archive
+    pub fn layout(&self) -> Vec<InstallTarget> {
+        let roots: Vec<&ZipModMod> = self
+            .manifests
+            .iter()
+            .filter(|zip_mod| {
+                !self
+                    .manifests
+                    .iter()
+                    .any(|other| other.dir() != zip_mod.dir() && zip_mod.dir().starts_with(other.dir()))
+            })
+            .collect();
+
+        let mut targets: Vec<InstallTarget> = vec![];
+        for zip_mod in roots {
+            let name = match zip_mod.dir().file_name() {
+                Some(name) => name.to_string_lossy().to_string(),
+                None => zip_mod.manifest.name.clone(),
+            };
+            let mut folder = sensible_folder_name(&name);
+            if folder.is_empty() || targets.iter().any(|target| target.folder.eq_ignore_ascii_case(&folder)) {
+                folder = sensible_folder_name(&format!("{name} ({})", zip_mod.manifest.unique_id));
+            }
+            targets.push(InstallTarget {
+                source: zip_mod.dir().to_path_buf(),
+                folder,
+                unique_id: zip_mod.manifest.unique_id.clone(),
+            });
+        }
+        targets
+    }
+
+    /// the manifests of the mods in `dirs`, every manifest for `None`
+    pub(crate) fn selected_manifests<'a>(
+        &'a self,
+        dirs: Option<&'a [PathBuf]>,
+    ) -> impl Iterator<Item = &'a ModManifest> {
+        self.manifests
+            .iter()
+            .filter(move |zip_mod| dirs.is_none_or(|dirs| dirs.iter().any(|dir| dir == zip_mod.dir())))
+            .map(|zip_mod| &zip_mod.manifest)
+    }
+
+    /// not installed as soon as one of the mods is missing, otherwise newer beats older. Archives without mods, like
+    /// ones that couldn't be read, are unknown
+    pub(crate) fn status(&self, installed: &[InstalledMod]) -> ZipStatus {
+        if self.manifests.is_empty() {
+            return ZipStatus::Unknown;
+        }
+        let mut status = ZipStatus::Installed;
+        for manifest in self.manifests() {
+            let Some(imod) = installed.iter().find(|imod| {
+                imod.manifest
+                    .unique_id
+                    .trim()
+                    .eq_ignore_ascii_case(manifest.unique_id.trim())
+            }) else {
+                return ZipStatus::NotInstalled;
+            };
+            match compare_versions(&manifest.version, &imod.manifest.version) {
+                Ordering::Greater => status = ZipStatus::Newer,
+                Ordering::Less if status == ZipStatus::Installed => status = ZipStatus::Older,
+                _ => {}
+            }
+        }
+        status
+    }
+}
+
+/// how the mods in a zip compare to the installed mods
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ZipStatus {
+    NotInstalled,
+    Installed,
+    Newer,
+    Older,
+    Unknown,
+}
+
+impl ZipStatus {
+    /// the status strings `ModsZipView` in `downloads.slint` knows about
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ZipStatus::NotInstalled => "not-installed",
+            ZipStatus::Installed => "installed",
+            ZipStatus::Newer => "newer",
+            ZipStatus::Older => "older",
+            ZipStatus::Unknown => "unknown",
+        }
+    }
+}
+
+/// compares versions like `1.2.10-beta` numerically part by part, falls back to comparing text
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    let parts = |version: &str| -> Vec<String> {
+        version
+            .trim()
+            .trim_start_matches(['v', 'V'])
+            .split(['.', '-', '+'])
+            .map(str::to_owned)
+            .collect()
+    };
+    let (a, b) = (parts(a), parts(b));
+
+    for i in 0..a.len().max(b.len()) {
+        // 1.0 and 1.0.0 are the same version
+        let a = a.get(i).map(String::as_str).unwrap_or("0");
+        let b = b.get(i).map(String::as_str).unwrap_or("0");
+        let ordering = match (a.parse::<u64>(), b.parse::<u64>()) {
+            (Ok(a), Ok(b)) => a.cmp(&b),
+            // a prerelease tag sorts before the release
+            (Ok(_), Err(_)) => Ordering::Greater,
+            (Err(_), Ok(_)) => Ordering::Less,
+            _ => a.to_ascii_lowercase().cmp(&b.to_ascii_lowercase()),
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    Ordering::Equal
 }
+
 // TODO: cache this
-fn has_manifest(entry: &PathBuf) -> Result<ZipMod> {
+pub fn has_manifest(entry: &PathBuf) -> Result<ZipMod> {
+    let zip = read_zip(entry)?;
+    if zip.manifests.is_empty() {
+        return Err(color_eyre::eyre::eyre!("No manifest found"));
+    }
+    Ok(zip)
+}
+
+/// every manifest in the zip at `entry`, broken ones included
+fn read_zip(entry: &PathBuf) -> Result<ZipMod> {
     let file = File::open(entry)?;
-    let created_at = file.metadata()?.created()?;
+    let metadata = file.metadata()?;
+    // not every filesystem knows when a file was created
+    let created_at = metadata.created().or_else(|_| metadata.modified())?;
     let mut archive = ZipArchive::new(file)?;
 
     let mut mods: Vec<ZipModMod> = Vec::new();
@@ -37,38 +261,96 @@ fn has_manifest(entry: &PathBuf) -> Result<ZipMod> {
     for i in 0..archive.len() {
         let mut file = archive.by_index(i)?;
         let file_name = file.name().to_string();
+        let path = Path::new(&file_name);
+        if is_junk(path) {
+            continue;
+        }
 
-        if file_name.ends_with("manifest.json") {
+        if path
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().eq_ignore_ascii_case("manifest.json"))
+        {
             let mut content = String::new();
-            file.read_to_string(&mut content)?;
+            let manifest = match file.read_to_string(&mut content) {
+                Ok(_) => parse_manifest(&content),
+                Err(err) => Err(Diagnostic::error(tr!("Can't read manifest.json: {}", err))),
+            };
+            mods.push(ZipModMod::new(Path::new(&file_name).to_path_buf(), manifest));
+        }
+    }
 
-            let manifest = json5::from_str(&content)?;
+    Ok(ZipMod {
+        created_at,
+        path: entry.clone(),
+        manifests: mods,
+        error: None,
+    })
+}
 
-            mods.push(ZipModMod {
-                manifest_path: Path::new(&file_name).to_path_buf(),
-                manifest,
-            });
+/// like [`has_manifest`] for an unpacked folder, the manifest paths are relative to `dir`
+pub fn folder_manifests(dir: &Path) -> Result<ZipMod> {
+    let created_at = dir.metadata()?.modified()?;
+    let mut mods: Vec<ZipModMod> = Vec::new();
+
+    for entry in WalkDir::new(dir).max_depth(5).sort_by_file_name() {
+        let entry = entry?;
+        let relative_path = entry.path().strip_prefix(dir)?;
+        if !entry.file_type().is_file() || is_junk(relative_path) {
+            continue;
+        }
+        if entry
+            .file_name()
+            .to_string_lossy()
+            .eq_ignore_ascii_case("manifest.json")
+        {
+            mods.push(ZipModMod::new(relative_path.to_path_buf(), read_manifest(entry.path())));
         }
     }
 
     if mods.is_empty() {
-        return Err(color_eyre::eyre::eyre!("No manifest found"));
+        return Err(color_eyre::eyre::eyre!("No manifest found in {dir:?}"));
     }
 
     Ok(ZipMod {
         created_at,
-        path: entry.clone(),
+        path: dir.to_path_buf(),
         manifests: mods,
+        error: None,
     })
 }
 
+/// the mods in a zip or an unpacked folder
+pub fn read_mod_source(path: &Path) -> Result<ZipMod> {
+    if path.is_dir() {
+        folder_manifests(path)
+    } else {
+        has_manifest(&path.to_path_buf())
+    }
+}
+
 pub fn find_zips_with_manifests(base_dir: &Path) -> Vec<ZipMod> {
     WalkDir::new(base_dir)
         .max_depth(2)
         .into_iter()
         .filter_map(|e| e.ok())
         .filter(|e| e.path().extension() == Some(std::ffi::OsStr::new("zip")))
-        .filter_map(|e: DirEntry| has_manifest(&e.into_path()).ok())
+        .filter_map(|e: DirEntry| {
+            let path = e.into_path();
+            match read_zip(&path) {
+                // not a mod
+                Ok(zip) if zip.manifests.is_empty() => None,
+                Ok(zip) => Some(zip),
+                Err(err) => {
+                    warn!("Failed to read {path:?}: {err}");
+                    Some(ZipMod {
+                        created_at: SystemTime::UNIX_EPOCH,
+                        path,
+                        manifests: vec![],
+                        error: Some(err.to_string()),
+                    })
+                }
+            }
+        })
         .collect()
 }
 
@@ -85,17 +367,246 @@ impl From<&ZipMod> for ModsZip {
                 manifest: x.manifest,
                 modified: value.created_at,
                 path: x.manifest_path,
+                group: None,
+                diagnostics: x.diagnostics,
             })
             .collect();
+        let mods = mods_to_modelrc(&mods);
+        // everything gets installed until the user unticks something
+        for index in 0..mods.row_count() {
+            let mut zip_mod = mods.row_data(index).unwrap();
+            zip_mod.selected = true;
+            mods.set_row_data(index, zip_mod);
+        }
 
         ModsZip {
-            mods: mods_to_modelrc(&mods),
+            selected_count: mods.row_count() as i32,
+            layout: generic_to_modelrc(
+                &value
+                    .layout()
+                    .iter()
+                    .map(|target| match target.source.to_string_lossy().as_ref() {
+                        "" => format!("Mods/{}", target.folder),
+                        source => tr!("Mods/{} from {}", target.folder, source),
+                    })
+                    .collect::<Vec<_>>(),
+            ),
+            mods,
             created: OffsetDateTime::from(value.created_at)
                 .format(desc)
                 .expect("Failed formatting date")
                 .into(),
             name: value.path.file_name().unwrap().to_string_lossy().to_string().into(),
             path: value.path.to_string_lossy().to_string().into(),
+            status: ZipStatus::NotInstalled.as_str().into(),
+            error: value.error.clone().unwrap_or_default().into(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use zip::{write::FileOptions, ZipWriter};
+
+    use super::*;
+
+    fn manifest(name: &str, id: &str) -> String {
+        format!(r#"{{"Name": "{name}", "Author": "someone", "Version": "1.0.0", "UniqueID": "{id}"}}"#)
+    }
+
+    /// a zip in `dir` with `files` as (path, content) pairs
+    fn write_zip(dir: &Path, files: &[(&str, &str)]) -> ZipMod {
+        let path = dir.join("mods.zip");
+        let mut zip = ZipWriter::new(File::create(&path).unwrap());
+        for (name, content) in files {
+            zip.start_file(*name, FileOptions::default()).unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+        has_manifest(&path).unwrap()
+    }
+
+    fn targets(zip: &ZipMod) -> Vec<(String, String)> {
+        zip.layout()
+            .into_iter()
+            .map(|target| (target.source.to_string_lossy().to_string(), target.folder))
+            .collect()
+    }
+
+    #[test]
+    fn mod_roots_become_folders() {
+        let dir = tempfile::tempdir().unwrap();
+
+        // a single wrapper folder keeps its name
+        let zip = write_zip(
+            dir.path(),
+            &[(
+                "Better Crafting/manifest.json",
+                &manifest("Better Crafting", "A.Crafting"),
+            )],
+        );
+        assert_eq!(targets(&zip), [("Better Crafting".into(), "Better Crafting".into())]);
+
+        // a manifest at the root gets a folder named after the mod
+        let zip = write_zip(dir.path(), &[("manifest.json", &manifest("Loose: Mod?", "A.Loose"))]);
+        assert_eq!(targets(&zip), [("".into(), "Loose_ Mod_".into())]);
+    }
+
+    #[test]
+    fn junk_is_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip = write_zip(
+            dir.path(),
+            &[
+                ("Mod/manifest.json", &manifest("Mod", "A.Mod")),
+                ("__MACOSX/Mod/manifest.json", "not json"),
+                ("Mod/._manifest.json", "not json"),
+                ("Mod/.DS_Store", ""),
+            ],
+        );
+        assert_eq!(zip.manifests().count(), 1);
+        assert_eq!(targets(&zip), [("Mod".into(), "Mod".into())]);
+
+        assert!(is_junk(Path::new("__MACOSX/Mod/content.json")));
+        assert!(is_junk(Path::new("Mod/Thumbs.db")));
+        assert!(is_junk(Path::new("Mod/desktop.INI")));
+        assert!(!is_junk(Path::new("Mod/assets/.hidden.json")));
+    }
+
+    #[test]
+    fn nested_manifests_belong_to_their_parent() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip = write_zip(
+            dir.path(),
+            &[
+                ("Pack/manifest.json", &manifest("Pack", "A.Pack")),
+                ("Pack/[CP] Pack/manifest.json", &manifest("Pack CP", "A.Pack.CP")),
+            ],
+        );
+        assert_eq!(zip.manifests().count(), 2);
+        assert_eq!(targets(&zip), [("Pack".into(), "Pack".into())]);
+    }
+
+    #[test]
+    fn every_mod_of_a_bundle_gets_a_folder() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip = write_zip(
+            dir.path(),
+            &[
+                (
+                    "Bundle/[CP] Bundle/manifest.json",
+                    &manifest("Bundle CP", "A.Bundle.CP"),
+                ),
+                (
+                    "Bundle/[JA] Bundle/manifest.json",
+                    &manifest("Bundle JA", "A.Bundle.JA"),
+                ),
+            ],
+        );
+        assert_eq!(
+            targets(&zip),
+            [
+                ("Bundle/[CP] Bundle".into(), "[CP] Bundle".into()),
+                ("Bundle/[JA] Bundle".into(), "[JA] Bundle".into()),
+            ]
+        );
+        let ids: Vec<_> = zip.layout().into_iter().map(|target| target.unique_id).collect();
+        assert_eq!(ids, ["A.Bundle.CP", "A.Bundle.JA"]);
+    }
+
+    #[test]
+    fn clashing_folder_names_get_the_unique_id() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip = write_zip(
+            dir.path(),
+            &[
+                ("Summer/Seasonal/manifest.json", &manifest("Summer", "A.Summer")),
+                ("Winter/seasonal/manifest.json", &manifest("Winter", "A.Winter")),
+            ],
+        );
+        assert_eq!(
+            targets(&zip),
+            [
+                ("Summer/Seasonal".into(), "Seasonal".into()),
+                ("Winter/seasonal".into(), "seasonal (A.Winter)".into()),
+            ]
+        );
+    }
+
+    fn installed(id: &str, version: &str) -> InstalledMod {
+        let manifest = serde_json::json!({"Name": id, "Author": "someone", "Version": version, "UniqueID": id});
+        InstalledMod {
+            path: PathBuf::from(id),
+            active: true,
+            modified: SystemTime::UNIX_EPOCH,
+            manifest: serde_json::from_value(manifest).unwrap(),
+            group: None,
+            diagnostics: vec![],
+        }
+    }
+
+    #[test]
+    fn status_compares_with_the_installed_mods() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip = write_zip(
+            dir.path(),
+            &[
+                ("A/manifest.json", &manifest("A", "Someone.A")),
+                ("B/manifest.json", &manifest("B", "Someone.B")),
+            ],
+        );
+        let status = |mods: &[InstalledMod]| zip.status(mods);
+        assert_eq!(status(&[installed("Someone.A", "1.0.0")]), ZipStatus::NotInstalled);
+        assert_eq!(
+            status(&[installed("someone.a", "1.0"), installed("Someone.B", "1.0.0")]),
+            ZipStatus::Installed
+        );
+        assert_eq!(
+            status(&[installed("Someone.A", "0.9.0"), installed("Someone.B", "1.1.0")]),
+            ZipStatus::Newer
+        );
+        assert_eq!(
+            status(&[installed("Someone.A", "1.0.0"), installed("Someone.B", "1.1.0")]),
+            ZipStatus::Older
+        );
+
+        let unreadable = ZipMod {
+            path: dir.path().join("broken.zip"),
+            created_at: SystemTime::UNIX_EPOCH,
+            manifests: vec![],
+            error: Some("invalid Zip archive".to_owned()),
+        };
+        assert_eq!(
+            unreadable.status(&[installed("Someone.A", "1.0.0")]),
+            ZipStatus::Unknown
+        );
+    }
+
+    #[test]
+    fn versions_compare_part_by_part() {
+        use Ordering::*;
+        assert_eq!(compare_versions("1.10.0", "1.9.0"), Greater);
+        assert_eq!(compare_versions("v1.2", "1.2.0"), Equal);
+        assert_eq!(compare_versions("1.2", "1.2.1"), Less);
+        assert_eq!(compare_versions("2", "1.99.99"), Greater);
+        // a prerelease comes before its release
+        assert_eq!(compare_versions("1.2.0-beta", "1.2.0"), Less);
+        assert_eq!(compare_versions("1.2.0-beta.2", "1.2.0-beta.10"), Less);
+        assert_eq!(compare_versions("1.2.0-alpha", "1.2.0-Beta"), Less);
+        assert_eq!(compare_versions("1.3.0-beta", "1.2.0"), Greater);
+        // parts that aren't numbers sort below numbers and by text among themselves
+        assert_eq!(compare_versions("1.x", "1.0"), Less);
+        assert_eq!(compare_versions("1.2+build", "1.2+BUILD"), Equal);
+        assert_eq!(compare_versions(" 1.0.0 ", "1.0.0"), Equal);
+    }
+
+    #[test]
+    fn folder_names_work_everywhere() {
+        assert_eq!(sensible_folder_name(r#"a<b>c:d"e/f\g|h?i*j"#), "a_b_c_d_e_f_g_h_i_j");
+        assert_eq!(sensible_folder_name("  Mod. . "), "Mod");
+        assert_eq!(sensible_folder_name("Tab\tMod"), "Tab_Mod");
+        assert_eq!(sensible_folder_name("..."), "");
+    }
+}
@@ -0,0 +1,361 @@
+use std::{
+    cell::RefCell,
+    fs::{create_dir_all, read_to_string, remove_file, rename, write},
+    future::Future,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use color_eyre::{eyre::eyre, Result};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+
+/// a single filesystem change that knows how to undo itself
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum Change {
+    Move {
+        from: PathBuf,
+        to: PathBuf,
+    },
+    Write {
+        path: PathBuf,
+        before: Option<String>,
+        after: String,
+    },
+}
+
+impl Change {
+    /// the paths the change reads or writes
+    fn paths(&self) -> Vec<&Path> {
+        match self {
+            Change::Move { from, to } => vec![from, to],
+            Change::Write { path, .. } => vec![path],
+        }
+    }
+
+    /// moves that already happened are skipped so a half finished undo can be retried
+    fn apply(&self, reverse: bool) -> Result<()> {
+        match self {
+            Change::Move { from, to } => {
+                let (from, to) = if reverse { (to, from) } else { (from, to) };
+                if !from.try_exists()? && to.try_exists()? {
+                    debug!("{to:?} is already in place");
+                    return Ok(());
+                }
+                if to.try_exists()? {
+                    Err(eyre!("{to:?} already exists"))?;
+                }
+                if let Some(parent) = to.parent() {
+                    create_dir_all(parent)?;
+                }
+                rename(from, to)?;
+            }
+            Change::Write { path, before, after } => match (reverse, before) {
+                (true, Some(content)) => write(path, content)?,
+                (true, None) => {
+                    if path.try_exists()? {
+                        remove_file(path)?;
+                    }
+                }
+                (false, _) => write(path, after)?,
+            },
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Operation {
+    pub description: String,
+    /// unix seconds
+    pub at: u64,
+    pub changes: Vec<Change>,
+}
+
+tokio::task_local! {
+    /// changes made by the operation running on this task, every operation gets its own list so two of them running
+    /// at once don't end up in each others undo step
+    static PENDING: RefCell<Vec<Change>>;
+}
+
+/// runs `future` and collects the changes it made, [`History::commit`] turns them into one undoable operation
+pub async fn recording<T>(future: impl Future<Output = T>) -> (T, Vec<Change>) {
+    PENDING
+        .scope(RefCell::new(vec![]), async {
+            let result = future.await;
+            (result, PENDING.with(RefCell::take))
+        })
+        .await
+}
+
+/// renames and remembers the move for the operation being [`recording`]
+pub fn rename_logged(from: impl AsRef<Path>, to: impl AsRef<Path>) -> Result<()> {
+    let (from, to) = (from.as_ref(), to.as_ref());
+    rename(from, to)?;
+    record(Change::Move {
+        from: from.to_path_buf(),
+        to: to.to_path_buf(),
+    });
+    Ok(())
+}
+
+/// writes and remembers what was there before for the operation being [`recording`]
+pub fn write_logged(path: impl AsRef<Path>, content: impl AsRef<str>) -> Result<()> {
+    let path = path.as_ref();
+    let before = read_to_string(path).ok();
+    write(path, content.as_ref())?;
+    record(Change::Write {
+        path: path.to_path_buf(),
+        before,
+        after: content.as_ref().to_owned(),
+    });
+    Ok(())
+}
+
+/// runs `f` on the blocking thread pool, the changes it records go to the operation this task is [`recording`]
+pub async fn spawn_blocking<T: Send + 'static>(f: impl FnOnce() -> T + Send + 'static) -> Result<T> {
+    let recording = PENDING.try_with(|_| ()).is_ok();
+    let (result, changes) = tokio::task::spawn_blocking(move || {
+        if !recording {
+            return (f(), vec![]);
+        }
+        PENDING.sync_scope(RefCell::new(vec![]), || {
+            let result = f();
+            (result, PENDING.with(RefCell::take))
+        })
+    })
+    .await?;
+    for change in changes {
+        record(change);
+    }
+    Ok(result)
+}
+
+/// for changes that already happened, like extracting a zip
+pub fn record(change: Change) {
+    if PENDING.try_with(|pending| pending.borrow_mut().push(change)).is_err() {
+        debug!("Not recording a change made outside of an operation");
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct History {
+    #[serde(skip)]
+    path: PathBuf,
+    #[serde(skip)]
+    limit: usize,
+    pub undo: Vec<Operation>,
+    pub redo: Vec<Operation>,
+}
+
+impl History {
+    pub fn load(path: PathBuf, limit: usize) -> Self {
+        let history = match read_to_string(&path) {
+            Ok(content) => ron::from_str(&content).unwrap_or_else(|err| {
+                warn!("Ignoring corrupt history {path:?}: {err}");
+                Default::default()
+            }),
+            Err(_) => Default::default(),
+        };
+        Self { path, limit, ..history }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        write(&self.path, ron::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// turns the changes of one [`recording`] into one undoable operation, does nothing when nothing changed
+    pub fn commit(&mut self, description: impl Into<String>, changes: Vec<Change>) -> Result<()> {
+        if changes.is_empty() {
+            return Ok(());
+        }
+
+        let operation = Operation {
+            description: description.into(),
+            at: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs(),
+            changes,
+        };
+        info!(
+            "Recorded {} ({} changes)",
+            operation.description,
+            operation.changes.len()
+        );
+        self.undo.push(operation);
+        self.redo.clear();
+        if self.undo.len() > self.limit {
+            self.undo.drain(..self.undo.len() - self.limit);
+        }
+        self.save()
+    }
+
+    /// drops the operations that touch anything inside the `removed` folders, together with the older ones that can
+    /// only be undone after them. For things deleted for good, like pruned versions
+    pub fn forget(&mut self, removed: &[PathBuf]) -> Result<()> {
+        let touches = |operation: &Operation| {
+            operation.changes.iter().any(|change| {
+                change
+                    .paths()
+                    .iter()
+                    .any(|path| removed.iter().any(|dir| path.starts_with(dir)))
+            })
+        };
+        let mut forgotten = 0;
+        // both stacks get popped from the end, everything in front of a broken operation is stuck behind it
+        for operations in [&mut self.undo, &mut self.redo] {
+            if let Some(index) = operations.iter().rposition(touches) {
+                forgotten += index + 1;
+                operations.drain(..=index);
+            }
+        }
+        if forgotten == 0 {
+            return Ok(());
+        }
+        info!("Forgetting {forgotten} steps that need deleted folders");
+        self.save()
+    }
+
+    /// forgets every operation, for changes that move folders out from under the recorded paths
+    pub fn clear(&mut self) -> Result<()> {
+        info!("Clearing {} undo and {} redo steps", self.undo.len(), self.redo.len());
+        self.undo.clear();
+        self.redo.clear();
+        self.save()
+    }
+
+    /// reverts the newest operation, returns its description
+    pub fn undo(&mut self) -> Result<Option<String>> {
+        let Some(operation) = self.undo.pop() else {
+            return Ok(None);
+        };
+        info!("Undoing {}", operation.description);
+        for change in operation.changes.iter().rev() {
+            if let Err(err) = change.apply(true) {
+                // keep it around so the user can fix the problem and try again
+                self.undo.push(operation);
+                return Err(err);
+            }
+        }
+        let description = operation.description.clone();
+        self.redo.push(operation);
+        self.save()?;
+        Ok(Some(description))
+    }
+
+    /// repeats the newest undone operation, returns its description
+    pub fn redo(&mut self) -> Result<Option<String>> {
+        let Some(operation) = self.redo.pop() else {
+            return Ok(None);
+        };
+        info!("Redoing {}", operation.description);
+        for change in &operation.changes {
+            if let Err(err) = change.apply(false) {
+                self.redo.push(operation);
+                return Err(err);
+            }
+        }
+        let description = operation.description.clone();
+        self.undo.push(operation);
+        self.save()?;
+        Ok(Some(description))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn blocking_work_records_into_the_operation() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file");
+        let (result, changes) = recording(async {
+            write_logged(dir.path().join("before"), "a")?;
+            let path = path.clone();
+            spawn_blocking(move || write_logged(path, "b")).await??;
+            Ok::<_, color_eyre::Report>(())
+        })
+        .await;
+        assert!(result.is_ok());
+        assert_eq!(changes.len(), 2);
+        assert!(matches!(&changes[1], Change::Write { path: written, .. } if *written == path));
+
+        // outside of an operation it just runs
+        spawn_blocking(move || write_logged(dir.path().join("outside"), "c"))
+            .await
+            .unwrap()
+            .unwrap();
+    }
+
+    #[test]
+    fn forgetting_a_folder_drops_the_steps_behind_it() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mods, versions) = (dir.path().join("Mods"), dir.path().join("versions"));
+        let step = |name: &str, from: PathBuf| Operation {
+            description: name.to_owned(),
+            at: 0,
+            changes: vec![Change::Move {
+                from,
+                to: mods.join(name),
+            }],
+        };
+        let mut history = History::load(dir.path().join("history.ron"), 10);
+        history.undo = vec![
+            step("first", dir.path().join("downloads")),
+            step("archived", versions.join("a").join("1-1.0.0").join("A")),
+            step("latest", dir.path().join("downloads")),
+        ];
+        history.redo = vec![step("redo", versions.join("b").join("2-1.0.0").join("B"))];
+
+        history.forget(&[versions.join("a").join("1-1.0.0")]).unwrap();
+        let names = |operations: &[Operation]| -> Vec<String> {
+            operations
+                .iter()
+                .map(|operation| operation.description.clone())
+                .collect()
+        };
+        assert_eq!(names(&history.undo), ["latest"]);
+        assert_eq!(names(&history.redo), ["redo"]);
+
+        history.forget(&[versions.join("b")]).unwrap();
+        assert!(history.redo.is_empty());
+        assert_eq!(History::load(dir.path().join("history.ron"), 10).undo.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn concurrent_operations_record_their_own_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let operation = |name: &'static str| {
+            let dir = dir.path().to_owned();
+            tokio::spawn(recording(async move {
+                for i in 0..3 {
+                    write_logged(dir.join(format!("{name}{i}")), name)?;
+                    tokio::task::yield_now().await;
+                }
+                Ok::<_, color_eyre::Report>(())
+            }))
+        };
+        let (first, second) = (operation("a"), operation("b"));
+        let (first, second) = (first.await.unwrap(), second.await.unwrap());
+
+        let paths = |changes: &[Change]| -> Vec<PathBuf> {
+            changes
+                .iter()
+                .map(|change| match change {
+                    Change::Write { path, .. } => path.strip_prefix(dir.path()).unwrap().to_owned(),
+                    Change::Move { .. } => unreachable!(),
+                })
+                .collect()
+        };
+        assert!(first.0.is_ok() && second.0.is_ok());
+        assert_eq!(paths(&first.1), ["a0", "a1", "a2"].map(PathBuf::from));
+        assert_eq!(paths(&second.1), ["b0", "b1", "b2"].map(PathBuf::from));
+
+        // undo puts back what was there before
+        let mut history = History::load(dir.path().join("history.ron"), 10);
+        history.commit("write a", first.1).unwrap();
+        assert_eq!(history.undo().unwrap().as_deref(), Some("write a"));
+        assert!(!dir.path().join("a0").exists());
+        assert!(dir.path().join("b0").exists());
+    }
+}
@@ -0,0 +1,426 @@
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    fmt::{Display, Write},
+    fs::{create_dir_all, write},
+    path::Path,
+    process::Command,
+};
+
+use color_eyre::Result;
+use log::{debug, info};
+use once_cell::sync::OnceCell;
+
+/// the catalogs in `translations/` by language code, english is the source language so it has none
+const CATALOGS: &[(&str, &str)] = &[("de", include_str!("../translations/de.po"))];
+
+/// the catalog core messages are translated with, unset for english
+static CATALOG: OnceCell<Catalog> = OnceCell::new();
+
+/// the locale variables `init` changed for gettext with the values they had before
+static SYSTEM_ENV: OnceCell<Vec<(&str, Option<OsString>)>> = OnceCell::new();
+
+/// translates a core message, `{}` and `{0}` placeholders get filled with the arguments like `@tr` does in the ui
+macro_rules! tr {
+    ($message:literal $(, $arg:expr)* $(,)?) => {{
+        // a let drops the borrowed arguments right away, they aren't Send and may sit in front of an await
+        let translated = $crate::i18n::translate($message, &[$(&$arg as &dyn std::fmt::Display),*]);
+        translated
+    }};
+}
+pub(crate) use tr;
+
+#[derive(Debug, Default)]
+struct Entry {
+    /// slint uses the component name as context, core messages have none
+    context: Option<String>,
+    id: String,
+    plural: Option<String>,
+    translations: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+struct Catalog {
+    /// the `msgid ""` entry, gettext reads the charset from it
+    header: String,
+    entries: Vec<Entry>,
+    /// message id to translation of the entries without context
+    core: HashMap<String, String>,
+}
+
+/// the text of a quoted po string, `"a\"b"` is `a"b`
+fn unquote(text: &str) -> String {
+    let text = text.trim();
+    let text = text
+        .strip_prefix('"')
+        .and_then(|text| text.strip_suffix('"'))
+        .unwrap_or(text);
+    let mut output = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            output.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => output.push('\n'),
+            Some('t') => output.push('\t'),
+            Some(other) => output.push(other),
+            None => {}
+        }
+    }
+    output
+}
+
+/// the part of an entry continuation lines get appended to
+#[derive(Clone, Copy)]
+enum Field {
+    Context,
+    Id,
+    Plural,
+    Translation,
+}
+
+impl Entry {
+    fn field(&mut self, field: Field) -> &mut String {
+        match field {
+            Field::Context => self.context.get_or_insert_with(String::new),
+            Field::Id => &mut self.id,
+            Field::Plural => self.plural.get_or_insert_with(String::new),
+            Field::Translation => self.translations.last_mut().unwrap(),
+        }
+    }
+}
+
+impl Catalog {
+    /// reads the subset of the po format translators produce, fuzzy and untranslated entries are skipped
+    fn parse(po: &str) -> Self {
+        let mut catalog = Catalog::default();
+        let mut entry = Entry::default();
+        let mut fuzzy = false;
+        let mut field = None;
+
+        for line in po.lines().map(str::trim) {
+            if line.starts_with('"') {
+                if let Some(field) = field {
+                    entry.field(field).push_str(&unquote(line));
+                }
+                continue;
+            }
+
+            let (keyword, value) = line.split_once(' ').unwrap_or((line, ""));
+            // the translations are the last part of an entry, anything else starts the next one
+            if !entry.translations.is_empty() && !keyword.starts_with("msgstr") {
+                catalog.add(std::mem::take(&mut entry), std::mem::take(&mut fuzzy));
+            }
+            field = match keyword {
+                "msgctxt" => Some(Field::Context),
+                "msgid" => Some(Field::Id),
+                "msgid_plural" => Some(Field::Plural),
+                _ if keyword.starts_with("msgstr") => {
+                    entry.translations.push(String::new());
+                    Some(Field::Translation)
+                }
+                _ => {
+                    fuzzy |= keyword == "#," && value.contains("fuzzy");
+                    None
+                }
+            };
+            if let Some(field) = field {
+                entry.field(field).push_str(&unquote(value));
+            }
+        }
+        catalog.add(entry, fuzzy);
+        catalog
+    }
+
+    fn add(&mut self, entry: Entry, fuzzy: bool) {
+        if entry.id.is_empty() && entry.context.is_none() {
+            self.header = entry.translations.into_iter().next().unwrap_or_default();
+            return;
+        }
+        if fuzzy || entry.translations.iter().all(String::is_empty) {
+            return;
+        }
+        if entry.context.is_none() {
+            self.core.insert(entry.id.clone(), entry.translations[0].clone());
+        }
+        self.entries.push(entry);
+    }
+
+    /// the catalog in gettext's binary `.mo` format, without the optional hash table
+    fn to_mo(&self) -> Vec<u8> {
+        let mut pairs: Vec<(String, String)> = self
+            .entries
+            .iter()
+            .map(|entry| {
+                let mut key = match &entry.context {
+                    Some(context) => format!("{context}\u{4}{}", entry.id),
+                    None => entry.id.clone(),
+                };
+                if let Some(plural) = &entry.plural {
+                    key = format!("{key}\0{plural}");
+                }
+                (key, entry.translations.join("\0"))
+            })
+            .collect();
+        pairs.push((String::new(), self.header.clone()));
+        // gettext binary searches the original strings
+        pairs.sort_by(|a, b| a.0.as_bytes().cmp(b.0.as_bytes()));
+
+        let count = pairs.len() as u32;
+        let originals_offset = 28;
+        let translations_offset = originals_offset + 8 * count;
+        let mut strings_offset = translations_offset + 8 * count;
+
+        let mut header = vec![];
+        for value in [
+            0x950412de,
+            0,
+            count,
+            originals_offset,
+            translations_offset,
+            0,
+            strings_offset,
+        ] {
+            header.extend_from_slice(&u32::to_le_bytes(value));
+        }
+        let mut tables = [vec![], vec![]];
+        let mut strings = vec![];
+        for index in [0, 1] {
+            for pair in &pairs {
+                let string = if index == 0 { &pair.0 } else { &pair.1 };
+                tables[index].extend_from_slice(&u32::to_le_bytes(string.len() as u32));
+                tables[index].extend_from_slice(&u32::to_le_bytes(strings_offset));
+                strings.extend_from_slice(string.as_bytes());
+                strings.push(0);
+                strings_offset += string.len() as u32 + 1;
+            }
+        }
+        [header, tables.concat(), strings].concat()
+    }
+}
+
+/// `de-DE`, `de_DE.UTF-8` and `de` all pick the `de` catalog
+fn find_catalog(language: &str) -> Option<(&'static str, &'static str)> {
+    let language = language
+        .split(['.', '@'])
+        .next()
+        .unwrap_or_default()
+        .replace('_', "-")
+        .to_ascii_lowercase();
+    let base = language.split('-').next().unwrap_or_default();
+    CATALOGS
+        .iter()
+        .find(|(code, _)| *code == language)
+        .or_else(|| CATALOGS.iter().find(|(code, _)| *code == base))
+        .copied()
+}
+
+/// picks the catalog for `language`, or the system locale when it's unset. The ui reads its strings through
+/// gettext, so the catalog gets written to `dir` as `<code>/LC_MESSAGES/<crate>.mo` for it
+pub fn init(language: Option<&str>, dir: &Path) -> Result<()> {
+    let language = language
+        .map(str::to_owned)
+        .or_else(sys_locale::get_locale)
+        .unwrap_or_else(|| "en".to_owned());
+    let Some((code, po)) = find_catalog(&language) else {
+        debug!("No catalog for {language}, staying with english");
+        return Ok(());
+    };
+
+    info!("Using the {code} catalog for {language}");
+    let catalog = Catalog::parse(po);
+    let messages = dir.join(code).join("LC_MESSAGES");
+    create_dir_all(&messages)?;
+    write(messages.join(concat!(env!("CARGO_PKG_NAME"), ".mo")), catalog.to_mo())?;
+
+    // gettext prefers LANGUAGE over the locale, which makes the language setting win over the system one. It's
+    // ignored under the C locale though, which is what an unset one means
+    let mut system_env = vec![("LANGUAGE", std::env::var_os("LANGUAGE"))];
+    std::env::set_var("LANGUAGE", code);
+    let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .find_map(|var| std::env::var(var).ok().filter(|value| !value.is_empty()));
+    if locale.is_none_or(|locale| locale == "C" || locale == "POSIX") {
+        system_env.push(("LC_ALL", std::env::var_os("LC_ALL")));
+        std::env::set_var("LC_ALL", "C.UTF-8");
+    }
+    let _ = SYSTEM_ENV.set(system_env);
+    slint::init_translations!(dir);
+    let _ = CATALOG.set(catalog);
+    Ok(())
+}
+
+/// undoes the locale changes `init` made for `command`, so programs we start see the system language again
+pub fn system_env(command: &mut Command) -> &mut Command {
+    for (var, value) in SYSTEM_ENV.get().into_iter().flatten() {
+        match value {
+            Some(value) => command.env(var, value),
+            None => command.env_remove(var),
+        };
+    }
+    command
+}
+
+/// fills `{}` placeholders in order and `{0}` ones by index, unknown ones are left as they are
+fn format(message: &str, args: &[&dyn Display]) -> String {
+    let mut output = String::new();
+    let mut rest = message;
+    let mut next = 0;
+    while let Some(start) = rest.find('{') {
+        output.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('}').map(|end| start + end) else {
+            output.push_str(&rest[start..]);
+            return output;
+        };
+        let name = &rest[start + 1..end];
+        let index = if name.is_empty() {
+            next += 1;
+            Some(next - 1)
+        } else {
+            name.parse().ok()
+        };
+        match index.and_then(|index| args.get(index)) {
+            Some(arg) => write!(output, "{arg}").unwrap(),
+            None => output.push_str(&rest[start..=end]),
+        }
+        rest = &rest[end + 1..];
+    }
+    output.push_str(rest);
+    output
+}
+
+/// use `tr!` instead, it keeps the message a literal so it can be extracted
+pub fn translate(message: &str, args: &[&dyn Display]) -> String {
+    let message = CATALOG
+        .get()
+        .and_then(|catalog| catalog.core.get(message))
+        .map_or(message, String::as_str);
+    format(message, args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PO: &str = r#"
+msgid ""
+msgstr ""
+"Content-Type: text/plain; charset=UTF-8\n"
+
+msgid "Say \"hi\"\tnow\\"
+msgstr "Sag \"hallo\"\tjetzt\\"
+
+msgid "Line one\n"
+"line two"
+msgstr "Zeile eins\n"
+"Zeile zwei"
+
+msgctxt "AppWindow"
+msgid "Mods"
+msgstr "Mods (ui)"
+
+msgid "Mods"
+msgstr "Modifikationen"
+
+msgid "{} mod"
+msgid_plural "{} mods"
+msgstr[0] "{} Mod"
+msgstr[1] "{} Mods"
+
+#, fuzzy
+msgid "Guessed"
+msgstr "Geraten"
+
+msgid "Untranslated"
+msgstr ""
+"#;
+
+    fn read_u32(mo: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(mo[offset..offset + 4].try_into().unwrap())
+    }
+
+    /// the strings of the originals (`table` 0) or translations (`table` 1) table
+    fn read_table(mo: &[u8], table: usize) -> Vec<String> {
+        let count = read_u32(mo, 8) as usize;
+        let offset = read_u32(mo, 12 + 4 * table) as usize;
+        (0..count)
+            .map(|index| {
+                let length = read_u32(mo, offset + 8 * index) as usize;
+                let start = read_u32(mo, offset + 8 * index + 4) as usize;
+                assert_eq!(mo[start + length], 0);
+                String::from_utf8(mo[start..start + length].to_vec()).unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn unescapes_strings() {
+        let catalog = Catalog::parse(PO);
+        assert_eq!(catalog.header, "Content-Type: text/plain; charset=UTF-8\n");
+        assert_eq!(catalog.core["Say \"hi\"\tnow\\"], "Sag \"hallo\"\tjetzt\\");
+        assert_eq!(catalog.core["Line one\nline two"], "Zeile eins\nZeile zwei");
+    }
+
+    #[test]
+    fn keeps_contexts_apart() {
+        let catalog = Catalog::parse(PO);
+        assert_eq!(catalog.core["Mods"], "Modifikationen");
+        let ui = catalog.entries.iter().find(|entry| entry.context.is_some()).unwrap();
+        assert_eq!(ui.context.as_deref(), Some("AppWindow"));
+        assert_eq!(ui.translations, ["Mods (ui)"]);
+    }
+
+    #[test]
+    fn reads_plurals() {
+        let catalog = Catalog::parse(PO);
+        let plural = catalog.entries.iter().find(|entry| entry.plural.is_some()).unwrap();
+        assert_eq!(plural.id, "{} mod");
+        assert_eq!(plural.plural.as_deref(), Some("{} mods"));
+        assert_eq!(plural.translations, ["{} Mod", "{} Mods"]);
+    }
+
+    #[test]
+    fn skips_fuzzy_and_untranslated_entries() {
+        let catalog = Catalog::parse(PO);
+        assert!(!catalog.core.contains_key("Guessed"));
+        assert!(!catalog.core.contains_key("Untranslated"));
+        assert_eq!(catalog.entries.len(), 5);
+    }
+
+    #[test]
+    fn writes_sorted_mo_tables() {
+        let mo = Catalog::parse(PO).to_mo();
+        assert_eq!(read_u32(&mo, 0), 0x950412de);
+        assert_eq!(read_u32(&mo, 4), 0);
+        assert_eq!(read_u32(&mo, 8), 6);
+        assert_eq!(read_u32(&mo, 12), 28);
+        assert_eq!(read_u32(&mo, 16), 28 + 8 * 6);
+
+        let originals = read_table(&mo, 0);
+        let translations = read_table(&mo, 1);
+        assert_eq!(
+            originals,
+            [
+                "",
+                "AppWindow\u{4}Mods",
+                "Line one\nline two",
+                "Mods",
+                "Say \"hi\"\tnow\\",
+                "{} mod\0{} mods",
+            ]
+        );
+        assert_eq!(translations[0], "Content-Type: text/plain; charset=UTF-8\n");
+        assert_eq!(translations[5], "{} Mod\0{} Mods");
+    }
+
+    #[test]
+    fn fills_placeholders() {
+        assert_eq!(format("{} of {}", &[&1, &"two"]), "1 of two");
+        assert_eq!(format("{1} before {0}", &[&"a", &"b"]), "b before a");
+        assert_eq!(format("{} and {5} and {name}", &[&1]), "1 and {5} and {name}");
+        assert_eq!(format("a{b", &[&1]), "a{b");
+        assert_eq!(format("{}{", &[&1]), "1{");
+    }
+}
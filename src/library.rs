@@ -0,0 +1,556 @@
+use std::{
+    fs::{self, create_dir_all, read_dir, read_link, read_to_string, remove_dir_all, rename, write, DirEntry, File},
+    io,
+    path::{Path, PathBuf},
+};
+
+use color_eyre::{eyre::eyre, Result};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use walkdir::WalkDir;
+
+use crate::{config::LinkMethod, history::History};
+
+/// a top level folder of the mods folder, stored in the library under the hash of its content at import time
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct LibraryEntry {
+    pub folder: String,
+    pub hash: String,
+}
+
+/// what a profile deploys into the mods folder, stored as `profiles/<profile>/library.ron`
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ProfileManifest {
+    pub mods: Vec<LibraryEntry>,
+}
+
+impl ProfileManifest {
+    pub fn path(profile_dir: &Path) -> PathBuf {
+        profile_dir.join("library.ron")
+    }
+
+    /// `None` for profiles that were never stored in the library
+    pub fn load(profile_dir: &Path) -> Result<Option<Self>> {
+        match read_to_string(Self::path(profile_dir)) {
+            Ok(content) => Ok(Some(ron::from_str(&content)?)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    pub fn save(&self, profile_dir: &Path) -> Result<()> {
+        write(
+            Self::path(profile_dir),
+            ron::ser::to_string_pretty(self, Default::default())?,
+        )?;
+        Ok(())
+    }
+}
+
+/// hashes the relative paths and contents of every file in `dir`, in a stable order
+pub fn hash_dir(dir: &Path) -> Result<String> {
+    let mut hasher = Sha256::new();
+    for entry in WalkDir::new(dir).follow_links(true).sort_by_file_name() {
+        let entry = entry?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let relative_path = entry.path().strip_prefix(dir)?.to_string_lossy().replace('\\', "/");
+        hasher.update(relative_path.as_bytes());
+        hasher.update([0]);
+        io::copy(&mut File::open(entry.path())?, &mut hasher)?;
+    }
+    // half a sha256 is plenty and keeps paths short on windows
+    Ok(format!("{:x}", hasher.finalize())[..32].to_owned())
+}
+
+pub struct Library {
+    path: PathBuf,
+    method: LinkMethod,
+}
+
+impl Library {
+    pub fn new(path: PathBuf, method: LinkMethod) -> Self {
+        Self { path, method }
+    }
+
+    fn entry_path(&self, hash: &str) -> PathBuf {
+        self.path.join(hash)
+    }
+
+    /// stashes the mods folder into the library, remembers it in `from_profile_dir` and deploys `entries`. When
+    /// anything fails the mods folder is put back the way it was, `.profile` only changes once everything worked.
+    /// A switch can't be undone and the operations in `history` name folders of the old deployment, so a successful
+    /// one clears it
+    pub fn switch(
+        &self,
+        mods_path: &Path,
+        from_profile_dir: &Path,
+        entries: &[LibraryEntry],
+        profile: &str,
+        history: &mut History,
+    ) -> Result<()> {
+        let stashed = self.stash(mods_path)?;
+        info!("Stashed {} folders in the library", stashed.len());
+
+        let result = ProfileManifest { mods: stashed.clone() }
+            .save(from_profile_dir)
+            .and_then(|_| self.deploy(entries, mods_path))
+            .and_then(|_| {
+                write_atomically(&mods_path.join(".profile"), profile).inspect_err(|_| {
+                    // deploy refuses to replace anything, so these folders are the ones it just made
+                    for entry in entries {
+                        let target = mods_path.join(&entry.folder);
+                        if target.is_dir() {
+                            let _ = remove_dir_all(target);
+                        }
+                    }
+                })
+            });
+        if let Err(err) = result {
+            warn!("Switching to {profile} failed, putting the previous mods back: {err}");
+            if let Err(restore_err) = self.deploy(&stashed, mods_path) {
+                warn!("Failed to put the previous mods back: {restore_err}");
+            }
+            return Err(err);
+        }
+        history.clear()
+    }
+
+    /// empties `dir` into the library and returns what was in it. Folders are hashed right before they're removed, so
+    /// only content that is byte for byte in the library gets deleted. On failure the folders stashed so far are
+    /// deployed again
+    pub fn stash(&self, dir: &Path) -> Result<Vec<LibraryEntry>> {
+        create_dir_all(&self.path)?;
+        let mut entries = vec![];
+
+        let result = (|| -> Result<()> {
+            for entry in read_dir(dir)? {
+                let entry = entry?;
+                if let Some(entry) = self.stash_folder(&entry)? {
+                    entries.push(entry);
+                }
+            }
+            Ok(())
+        })();
+
+        if let Err(err) = result {
+            warn!("Stashing {dir:?} failed, deploying {} folders again", entries.len());
+            if let Err(restore_err) = self.deploy(&entries, dir) {
+                warn!("Failed to deploy the stashed folders again: {restore_err}");
+            }
+            return Err(err);
+        }
+        Ok(entries)
+    }
+
+    fn stash_folder(&self, entry: &DirEntry) -> Result<Option<LibraryEntry>> {
+        let path = entry.path();
+        let folder = entry.file_name().to_string_lossy().to_string();
+
+        // whole folder links from before mods got deployed as folders of linked files
+        if entry.file_type()?.is_symlink() {
+            let target = read_link(&path)?;
+            if target.parent() != Some(self.path.as_path()) {
+                warn!("Leaving {path:?} alone, it links outside of the library");
+                return Ok(None);
+            }
+            let linked_hash = target.file_name().unwrap().to_string_lossy().to_string();
+            let hash = hash_dir(&target)?;
+            if hash != linked_hash {
+                // smapi wrote through the link, the other profiles already see the change
+                warn!("{folder} changed inside the library entry {linked_hash}, storing it as {hash}");
+                self.store_copy(&target, &hash)?;
+            }
+            remove_link(&path)?;
+            return Ok(Some(LibraryEntry { folder, hash }));
+        }
+        if !path.is_dir() {
+            return Ok(None);
+        }
+
+        let hash = hash_dir(&path)?;
+        let library_path = self.entry_path(&hash);
+        if library_path.try_exists()? {
+            // a deployed mod nobody changed, or a mod that's in another profile too
+            debug!("{folder} is already in the library as {hash}");
+            remove_dir_all(&path)?;
+        } else if is_private(&path)? {
+            info!("Adding {folder} to the library as {hash}");
+            rename(&path, &library_path)?;
+        } else {
+            // a deployed mod with a changed config, its other files still belong to another entry
+            info!("Adding a copy of {folder} to the library as {hash}");
+            self.store_copy(&path, &hash)?;
+            remove_dir_all(&path)?;
+        }
+        Ok(Some(LibraryEntry { folder, hash }))
+    }
+
+    /// copies `source` into the library as `hash`, following links so the entry shares nothing with other entries
+    fn store_copy(&self, source: &Path, hash: &str) -> Result<()> {
+        let library_path = self.entry_path(hash);
+        if library_path.try_exists()? {
+            return Ok(());
+        }
+        let partial = self.path.join(format!("{hash}.partial"));
+        if partial.try_exists()? {
+            remove_dir_all(&partial)?;
+        }
+        for entry in WalkDir::new(source).follow_links(true) {
+            let entry = entry?;
+            let destination = partial.join(entry.path().strip_prefix(source)?);
+            if entry.file_type().is_dir() {
+                create_dir_all(&destination)?;
+            } else {
+                fs::copy(entry.path(), &destination)?;
+            }
+        }
+        rename(&partial, &library_path)?;
+        Ok(())
+    }
+
+    /// puts every entry into the mods folder as a folder of linked files, falling back from symlinks to hardlinks to
+    /// copies. Either every entry gets deployed or none of them
+    pub fn deploy(&self, entries: &[LibraryEntry], mods_path: &Path) -> Result<()> {
+        let mut deployed = vec![];
+        let result = entries
+            .iter()
+            .try_for_each(|entry| self.deploy_entry(entry, mods_path, &mut deployed));
+
+        if result.is_err() {
+            for target in deployed.iter().rev() {
+                if let Err(err) = remove_dir_all(target) {
+                    warn!("Failed to remove {target:?} after a failed deploy: {err}");
+                }
+            }
+        }
+        result
+    }
+
+    fn deploy_entry(&self, entry: &LibraryEntry, mods_path: &Path, deployed: &mut Vec<PathBuf>) -> Result<()> {
+        let source = self.entry_path(&entry.hash);
+        let target = mods_path.join(&entry.folder);
+        if !source.try_exists()? {
+            warn!("{} ({}) is missing from the library", entry.folder, entry.hash);
+            return Ok(());
+        }
+        if target.symlink_metadata().is_ok() {
+            Err(eyre!("{target:?} already exists"))?;
+        }
+
+        let methods = match self.method {
+            LinkMethod::Symlink => &[LinkMethod::Symlink, LinkMethod::Hardlink, LinkMethod::Copy][..],
+            LinkMethod::Hardlink => &[LinkMethod::Hardlink, LinkMethod::Copy][..],
+            LinkMethod::Copy => &[LinkMethod::Copy][..],
+        };
+        let mut result = Err(eyre!("No link method for {target:?}"));
+        for method in methods {
+            result = link(&source, &target, *method);
+            match &result {
+                Ok(_) => break,
+                Err(err) => {
+                    warn!("Failed to deploy {} with {method:?}: {err}", entry.folder);
+                    // a half finished deploy
+                    if target.is_dir() {
+                        remove_dir_all(&target)?;
+                    }
+                }
+            }
+        }
+        result?;
+        deployed.push(target);
+        Ok(())
+    }
+}
+
+/// gives the files of a deployed folder their own copies, so writing to them doesn't change the library. Anything
+/// that overwrites files of a deployed mod, like reinstalling it, has to call this first
+pub fn detach(dir: &Path) -> Result<()> {
+    if dir.symlink_metadata()?.file_type().is_symlink() {
+        let target = read_link(dir)?;
+        let partial = dir.with_extension("partial");
+        link(&target, &partial, LinkMethod::Copy)?;
+        remove_link(dir)?;
+        rename(&partial, dir)?;
+        return Ok(());
+    }
+
+    for entry in WalkDir::new(dir) {
+        let entry = entry?;
+        if entry.file_type().is_dir() || is_private_file(&entry)? {
+            continue;
+        }
+        debug!("Detaching {:?} from the library", entry.path());
+        let partial = entry.path().with_extension("partial");
+        fs::copy(entry.path(), &partial)?;
+        fs::remove_file(entry.path())?;
+        rename(&partial, entry.path())?;
+    }
+    Ok(())
+}
+
+/// writes through a temporary file so readers never see half a file
+fn write_atomically(path: &Path, content: &str) -> Result<()> {
+    let partial = path.with_extension("partial");
+    write(&partial, content)?;
+    rename(&partial, path)?;
+    Ok(())
+}
+
+/// smapi writes the config of a mod next to its manifest, it always gets its own copy
+fn is_config(relative_path: &Path) -> bool {
+    relative_path
+        .to_str()
+        .is_some_and(|path| path.eq_ignore_ascii_case("config.json"))
+}
+
+fn link(source: &Path, target: &Path, method: LinkMethod) -> Result<()> {
+    for entry in WalkDir::new(source).follow_links(true) {
+        let entry = entry?;
+        let relative_path = entry.path().strip_prefix(source)?;
+        let destination = target.join(relative_path);
+        if entry.file_type().is_dir() {
+            create_dir_all(&destination)?;
+            continue;
+        }
+        match method {
+            _ if is_config(relative_path) => {
+                fs::copy(entry.path(), &destination)?;
+            }
+            LinkMethod::Symlink => {
+                #[cfg(unix)]
+                std::os::unix::fs::symlink(entry.path(), &destination)?;
+                #[cfg(windows)]
+                std::os::windows::fs::symlink_file(entry.path(), &destination)?;
+            }
+            LinkMethod::Hardlink => fs::hard_link(entry.path(), &destination)?,
+            LinkMethod::Copy => {
+                fs::copy(entry.path(), &destination)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// whether nothing in `dir` is shared with the library, those folders can be moved into it as they are
+fn is_private(dir: &Path) -> Result<bool> {
+    for entry in WalkDir::new(dir) {
+        let entry = entry?;
+        if !entry.file_type().is_dir() && !is_private_file(&entry)? {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+fn is_private_file(entry: &walkdir::DirEntry) -> Result<bool> {
+    if entry.path_is_symlink() {
+        return Ok(false);
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        Ok(entry.metadata()?.nlink() == 1)
+    }
+    // hardlinks can't be told apart from plain files here, copying is always safe
+    #[cfg(not(unix))]
+    Ok(false)
+}
+
+fn remove_link(path: &Path) -> io::Result<()> {
+    // windows directory symlinks are removed like directories
+    #[cfg(windows)]
+    return fs::remove_dir(path);
+    #[cfg(not(windows))]
+    fs::remove_file(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::Change;
+
+    fn write_mod(dir: &Path, folder: &str, config: &str) {
+        let root = dir.join(folder);
+        create_dir_all(root.join("assets")).unwrap();
+        write(root.join("manifest.json"), format!(r#"{{"UniqueID": "{folder}"}}"#)).unwrap();
+        write(root.join("assets").join("data.json"), folder).unwrap();
+        write(root.join("config.json"), config).unwrap();
+    }
+
+    fn sorted(mut entries: Vec<LibraryEntry>) -> Vec<LibraryEntry> {
+        entries.sort_by(|a, b| a.folder.cmp(&b.folder));
+        entries
+    }
+
+    fn folders(dir: &Path) -> Vec<String> {
+        let mut folders: Vec<String> = read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        folders.sort();
+        folders
+    }
+
+    #[test]
+    fn stash_and_deploy_round_trip() {
+        for method in [LinkMethod::Symlink, LinkMethod::Hardlink, LinkMethod::Copy] {
+            let dir = tempfile::tempdir().unwrap();
+            let mods = dir.path().join("Mods");
+            write_mod(&mods, "A", "{}");
+            write_mod(&mods, "B", "{}");
+            let hash_a = hash_dir(&mods.join("A")).unwrap();
+            let library = Library::new(dir.path().join("library"), method);
+
+            let entries = sorted(library.stash(&mods).unwrap());
+            assert_eq!(entries.len(), 2);
+            assert_eq!(entries[0].hash, hash_a);
+            assert!(folders(&mods).is_empty());
+
+            library.deploy(&entries, &mods).unwrap();
+            assert_eq!(folders(&mods), ["A", "B"]);
+            assert_eq!(
+                read_to_string(mods.join("A").join("assets").join("data.json")).unwrap(),
+                "A"
+            );
+            assert!(!mods.join("A").join("config.json").is_symlink());
+
+            // nothing changed, so nothing new ends up in the library
+            let again = sorted(library.stash(&mods).unwrap());
+            assert_eq!(
+                again.iter().map(|e| &e.hash).collect::<Vec<_>>(),
+                [&entries[0].hash, &entries[1].hash]
+            );
+            assert_eq!(folders(&dir.path().join("library")).len(), 2, "{method:?}");
+        }
+    }
+
+    #[test]
+    fn config_changes_stay_out_of_the_library() {
+        let dir = tempfile::tempdir().unwrap();
+        let mods = dir.path().join("Mods");
+        write_mod(&mods, "A", "{}");
+        let library = Library::new(dir.path().join("library"), LinkMethod::Symlink);
+        let entries = library.stash(&mods).unwrap();
+        library.deploy(&entries, &mods).unwrap();
+
+        // what smapi does when a mod saves its config
+        write(mods.join("A").join("config.json"), r#"{"Enabled": false}"#).unwrap();
+        let library_config = dir.path().join("library").join(&entries[0].hash).join("config.json");
+        assert_eq!(read_to_string(&library_config).unwrap(), "{}");
+
+        let changed = library.stash(&mods).unwrap();
+        assert_ne!(changed[0].hash, entries[0].hash);
+        assert_eq!(read_to_string(&library_config).unwrap(), "{}");
+        library.deploy(&changed, &mods).unwrap();
+        assert_eq!(
+            read_to_string(mods.join("A").join("config.json")).unwrap(),
+            r#"{"Enabled": false}"#
+        );
+        assert_eq!(hash_dir(&mods.join("A")).unwrap(), changed[0].hash);
+    }
+
+    #[test]
+    fn detach_keeps_reinstalls_out_of_the_library() {
+        let dir = tempfile::tempdir().unwrap();
+        let mods = dir.path().join("Mods");
+        write_mod(&mods, "A", "{}");
+        let library = Library::new(dir.path().join("library"), LinkMethod::Hardlink);
+        let entries = library.stash(&mods).unwrap();
+        library.deploy(&entries, &mods).unwrap();
+
+        detach(&mods.join("A")).unwrap();
+        write(mods.join("A").join("assets").join("data.json"), "new version").unwrap();
+        let library_entry = dir.path().join("library").join(&entries[0].hash);
+        assert_eq!(
+            read_to_string(library_entry.join("assets").join("data.json")).unwrap(),
+            "A"
+        );
+        assert_eq!(hash_dir(&library_entry).unwrap(), entries[0].hash);
+    }
+
+    #[test]
+    fn failed_switch_puts_the_mods_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let mods = dir.path().join("Mods");
+        let (profile_a, profile_b) = (dir.path().join("a"), dir.path().join("b"));
+        create_dir_all(&profile_a).unwrap();
+        write_mod(&profile_b.join("enabled"), "B", "{}");
+        write_mod(&mods, "A", "{}");
+        write(mods.join(".profile"), "a").unwrap();
+        let library = Library::new(dir.path().join("library"), LinkMethod::Symlink);
+        let entries_b = library.stash(&profile_b.join("enabled")).unwrap();
+
+        let mut history = History::load(dir.path().join("history.ron"), 10);
+
+        // a file in the way of B makes the deploy fail
+        write(mods.join("B"), "in the way").unwrap();
+        assert!(library
+            .switch(&mods, &profile_a, &entries_b, "b", &mut history)
+            .is_err());
+        assert_eq!(folders(&mods), [".profile", "A", "B"]);
+        assert_eq!(read_to_string(mods.join(".profile")).unwrap(), "a");
+        assert_eq!(read_to_string(mods.join("B")).unwrap(), "in the way");
+        assert_eq!(
+            read_to_string(mods.join("A").join("assets").join("data.json")).unwrap(),
+            "A"
+        );
+
+        // and once it's out of the way the switch goes through
+        fs::remove_file(mods.join("B")).unwrap();
+        library
+            .switch(&mods, &profile_a, &entries_b, "b", &mut history)
+            .unwrap();
+        assert_eq!(folders(&mods), [".profile", "B"]);
+        assert_eq!(read_to_string(mods.join(".profile")).unwrap(), "b");
+        let stashed_a = ProfileManifest::load(&profile_a).unwrap().unwrap();
+        assert_eq!(stashed_a.mods[0].folder, "A");
+
+        library
+            .switch(&mods, &profile_b, &stashed_a.mods, "a", &mut history)
+            .unwrap();
+        assert_eq!(folders(&mods), [".profile", "A"]);
+    }
+
+    #[test]
+    fn undo_after_a_switch_leaves_the_mods_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        let mods = dir.path().join("Mods");
+        let deleted = dir.path().join("deleted");
+        let (profile_a, profile_b) = (dir.path().join("a"), dir.path().join("b"));
+        create_dir_all(&profile_a).unwrap();
+        write_mod(&profile_b.join("enabled"), "B", "{}");
+        write_mod(&mods, "A", "{}");
+        write_mod(&mods, "Old", "{}");
+        let library = Library::new(dir.path().join("library"), LinkMethod::Symlink);
+        let entries_b = library.stash(&profile_b.join("enabled")).unwrap();
+
+        // deleting a mod before the switch, undoing it afterwards would move it into profile b's mods folder
+        let mut history = History::load(dir.path().join("history.ron"), 10);
+        create_dir_all(&deleted).unwrap();
+        rename(mods.join("Old"), deleted.join("Old")).unwrap();
+        let change = Change::Move {
+            from: mods.join("Old"),
+            to: deleted.join("Old"),
+        };
+        history.commit("Delete Old", vec![change]).unwrap();
+
+        // a failed switch leaves the mods folder as it was, so its history still applies
+        write(mods.join("B"), "in the way").unwrap();
+        assert!(library
+            .switch(&mods, &profile_a, &entries_b, "b", &mut history)
+            .is_err());
+        assert_eq!(history.undo.len(), 1);
+        fs::remove_file(mods.join("B")).unwrap();
+
+        library
+            .switch(&mods, &profile_a, &entries_b, "b", &mut history)
+            .unwrap();
+        assert_eq!(history.undo().unwrap(), None);
+        assert_eq!(folders(&mods), [".profile", "B"]);
+        assert!(deleted.join("Old").is_dir());
+        assert!(History::load(dir.path().join("history.ron"), 10).undo.is_empty());
+    }
+}
@@ -3,33 +3,68 @@
 use std::{
     collections::{HashMap, HashSet},
     ffi::OsStr,
-    fs::{create_dir_all, read_dir, read_to_string, rename, write, DirEntry, File},
+    fs::{create_dir_all, read_dir, read_to_string, remove_dir, write, DirEntry, File},
     future::Future,
     io,
-    path::PathBuf,
+    path::{Path, PathBuf},
+    sync::Mutex,
     time::{Instant, SystemTime, UNIX_EPOCH},
 };
 
 use ::config::{Config, Environment};
 use color_eyre::eyre::Result;
-use find_mods_from_downloads::{find_zips_with_manifests, ZipMod};
+use dependency_graph::DependencyGraph;
+use diagnostics::{placeholder_manifest, read_manifest, Diagnostic, Severity};
+use find_mods_from_downloads::{find_zips_with_manifests, is_junk, read_mod_source, InstallTarget, ZipMod};
 use futures::TryFutureExt;
-use log::{debug, error, info};
+use history::{rename_logged, write_logged, Change, History};
+use i18n::tr;
+use library::{Library, ProfileManifest};
+use log::{debug, error, info, warn};
+use mod_filter::{ModFacts, ModQuery};
+use mod_metadata::{parse_tags, MetadataStore, ModSource};
+use mod_versions::{keep_config, StoredVersion, VersionStore};
+use nexusapi::{NexusClient, NexusFile, NexusModDetails};
+use once_cell::sync::Lazy;
+use percent_encoding::percent_decode_str;
+use save_scan::scan_save;
+use saves::{default_saves_path, find_saves, SaveBackups, SaveBindings, SaveGame};
 use serde::{Deserialize, Serialize};
+use slint::winit_030::winit::event::WindowEvent;
+use slint::winit_030::{EventResult, WinitWindowAccessor};
 use slint::{Model, ModelRc, SharedString, VecModel, Weak};
-use smapiapi::{resolve_mods, SmapiMod};
+use smapiapi::{SmapiClient, SmapiMod, SmapiModMetadata};
+use time::{macros::format_description, OffsetDateTime};
 use tokio::{sync::OnceCell, task::JoinHandle};
 use walkdir::WalkDir;
 use zip::ZipArchive;
 
-use crate::{config::SVMMConfig, find_game::get_game_dir};
+use crate::{
+    config::{update_config_file, write_config_file, DisableMode, ProfileStorage, SVMMConfig},
+    find_game::get_game_dir,
+};
 
 slint::include_modules!();
 
 mod config;
+mod dependency_graph;
+mod diagnostics;
+mod downloads;
 mod find_game;
 mod find_mods_from_downloads;
+mod history;
+mod i18n;
+mod library;
+mod mod_filter;
+mod mod_metadata;
+mod mod_versions;
+mod nexusapi;
+mod save_scan;
+mod saves;
 mod smapiapi;
+#[cfg(test)]
+mod test_server;
+mod themes;
 
 const SVMM: &str = "SVMM";
 
@@ -40,10 +75,25 @@ struct GameData {
     svmm_path: PathBuf,
     profile_path: PathBuf,
     mods_path: PathBuf,
+    downloads_path: PathBuf,
+    config: SVMMConfig,
 }
 
 static GAME_DATA: OnceCell<GameData> = OnceCell::const_new();
 
+/// the last scanned mods, kept around so searching and filtering doesn't have to hit the disk
+#[derive(Default)]
+struct LoadedMods {
+    mods: Vec<InstalledMod>,
+    /// also holds the newest versions from the last update check, they survive reloads
+    facts: ModFacts,
+    /// lowercase unique ids of the mods ticked in the mod list
+    selected: HashSet<String>,
+    collapsed_groups: HashSet<String>,
+}
+
+static LOADED_MODS: Lazy<Mutex<LoadedMods>> = Lazy::new(Default::default);
+
 fn get_svmm_config() -> PathBuf {
     dirs::config_local_dir().unwrap().join("svmm.ron")
 }
@@ -81,6 +131,8 @@ async fn get_game_data<'a>() -> Result<&'a GameData> {
 
     let mods_dir = game_dir.join("Mods");
 
+    let downloads_dir = svmm_dir.join("downloads");
+
     info!("using svmm dir: {svmm_dir:?}");
 
     if !profile_dir.try_exists()? {
@@ -92,6 +144,10 @@ async fn get_game_data<'a>() -> Result<&'a GameData> {
         create_dir_all(&svmm_dir.join("deleted"))?;
     }
 
+    if !downloads_dir.try_exists()? {
+        create_dir_all(&downloads_dir)?;
+    }
+
     if read_dir(&profile_dir)?.count() == 0 {
         info!("Creating svmm default profiles");
         for i in 1..=3 {
@@ -107,6 +163,8 @@ async fn get_game_data<'a>() -> Result<&'a GameData> {
                 svmm_path: svmm_dir,
                 profile_path: profile_dir,
                 mods_path: mods_dir,
+                downloads_path: downloads_dir,
+                config,
             }
         })
         .await)
@@ -150,7 +208,7 @@ async fn get_profiles_names() -> Result<Vec<String>> {
         .collect())
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
 struct ModManifest {
     #[serde(rename = "Name")]
     #[serde(alias = "name")]
@@ -176,6 +234,42 @@ struct ModManifest {
     #[serde(alias = "update_keys")]
     #[serde(default)]
     update_keys: Vec<String>,
+    #[serde(rename = "ContentPackFor")]
+    #[serde(default)]
+    content_pack_for: Option<ContentPackFor>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct ContentPackFor {
+    #[serde(rename = "UniqueID")]
+    #[serde(alias = "UniqueId")]
+    unique_id: String,
+}
+
+impl ModManifest {
+    /// splits `UpdateKeys` like `Nexus:1234@subkey` into `("nexus", "1234")`
+    fn update_keys(&self) -> impl Iterator<Item = (String, &str)> {
+        self.update_keys.iter().filter_map(|entry| {
+            let (key, value) = entry.split_once(':')?;
+            let value = value.split('@').next().expect("Failed splitting at @");
+            Some((key.trim().to_ascii_lowercase(), value.trim()))
+        })
+    }
+
+    /// ids of every mod this one can't run without, content packs always need their framework
+    fn required_dependencies(&self) -> impl Iterator<Item = &str> {
+        self.dependencies
+            .iter()
+            .filter(|dependency| dependency.required)
+            .map(|dependency| dependency.unique_id.trim())
+            .chain(self.content_pack_for.iter().map(|framework| framework.unique_id.trim()))
+    }
+
+    fn nexus_id(&self) -> Option<u32> {
+        self.update_keys()
+            .find(|(key, _)| key == "nexus")
+            .and_then(|(_, value)| value.parse().ok())
+    }
 }
 
 #[allow(unused)]
@@ -188,34 +282,122 @@ struct ModDependency {
     #[serde(alias = "MinimumVersion")]
     version: Option<String>,
     #[serde(rename = "IsRequired")]
-    #[serde(default)]
+    #[serde(default = "default_true")]
     required: bool,
     #[serde(flatten)]
     other: HashMap<String, serde_json::Value>,
 }
 
+// smapi treats dependencies as required unless told otherwise
+fn default_true() -> bool {
+    true
+}
+
 #[derive(Clone, Debug)]
 struct InstalledMod {
     path: PathBuf,
     active: bool,
     modified: SystemTime,
     manifest: ModManifest,
+    /// the folder the mod sits in when it isn't directly in the mods folder, like `Artisan Valley` for
+    /// `Mods/Artisan Valley/[CP] Artisan Valley`
+    group: Option<String>,
+    /// problems found while scanning, mods that failed to load have a placeholder manifest and an error here
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl InstalledMod {
+    /// unique ids are case insensitive in smapi, a dependency on `pathoschild.contentpatcher` is content patcher
+    fn has_id(&self, id: &str) -> bool {
+        self.manifest.unique_id.trim().eq_ignore_ascii_case(id.trim())
+    }
+
+    fn errors(&self) -> impl Iterator<Item = &str> {
+        self.diagnostics(Severity::Error)
+    }
+
+    fn warnings(&self) -> impl Iterator<Item = &str> {
+        self.diagnostics(Severity::Warning)
+    }
+
+    fn diagnostics(&self, severity: Severity) -> impl Iterator<Item = &str> {
+        self.diagnostics
+            .iter()
+            .filter(move |diagnostic| diagnostic.severity == severity)
+            .map(|diagnostic| diagnostic.message.as_str())
+    }
+}
+
+/// smapi skips folders starting with a `.`, other tools disable mods that way
+fn is_hidden(relative_path: &Path) -> bool {
+    relative_path
+        .components()
+        .any(|component| component.as_os_str().to_string_lossy().starts_with('.'))
+}
+
+/// `relative_path` without the leading dots smapi uses to skip folders
+fn unhide(relative_path: &Path) -> PathBuf {
+    relative_path
+        .components()
+        .map(|component| {
+            component
+                .as_os_str()
+                .to_string_lossy()
+                .trim_start_matches('.')
+                .to_owned()
+        })
+        .collect()
 }
 
 async fn load_mods_from_dir(path: &PathBuf, active: bool) -> Result<Vec<InstalledMod>> {
     let mut result = Vec::new();
-    for entry in WalkDir::new(path).max_depth(3) {
-        let entry = entry?;
+    // library profiles link their mods into the mods folder
+    for entry in WalkDir::new(path).max_depth(3).follow_links(true) {
+        // one unreadable folder shouldn't hide every other mod
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(err) => {
+                warn!("Skipping {:?}: {err}", err.path());
+                continue;
+            }
+        };
 
         if entry.file_name() == OsStr::new("manifest.json") {
-            let manifest: ModManifest = json5::from_str(read_to_string(entry.path())?.as_str())?;
+            // impossible
+            let mod_path = entry.path().parent().unwrap().to_path_buf();
+            let relative_path = mod_path.strip_prefix(path)?;
+            let (manifest, diagnostics) = match read_manifest(entry.path()) {
+                Ok((manifest, warnings)) => {
+                    for warning in &warnings {
+                        debug!("{mod_path:?}: {}", warning.message);
+                    }
+                    (manifest, warnings)
+                }
+                Err(diagnostic) => {
+                    warn!("Broken mod {mod_path:?}: {}", diagnostic.message);
+                    (placeholder_manifest(&relative_path.to_string_lossy()), vec![diagnostic])
+                }
+            };
             debug!("found active mod: {} id: {}", &manifest.name, &manifest.unique_id);
+            // a dot disabled group is still the same group
+            let group = match relative_path.components().count() {
+                0 | 1 => None,
+                _ => unhide(relative_path)
+                    .components()
+                    .next()
+                    .map(|group| group.as_os_str().to_string_lossy().to_string()),
+            };
             let imod = InstalledMod {
-                // impossible
-                path: entry.path().parent().unwrap().to_path_buf(),
-                modified: entry.metadata()?.modified()?,
-                active,
+                active: active && !is_hidden(relative_path),
+                path: mod_path,
+                modified: entry
+                    .metadata()
+                    .ok()
+                    .and_then(|metadata| metadata.modified().ok())
+                    .unwrap_or(UNIX_EPOCH),
                 manifest,
+                group,
+                diagnostics,
             };
             result.push(imod);
         }
@@ -224,29 +406,111 @@ async fn load_mods_from_dir(path: &PathBuf, active: bool) -> Result<Vec<Installe
 
     Ok(result)
 }
+/// where a missing dependency can be installed from without going online
+#[derive(Clone, Debug)]
+enum LocalSource {
+    Disabled,
+    Zip(PathBuf),
+    Deleted(PathBuf),
+}
+
 #[derive(Clone, Debug)]
 struct ResolvedMissingDependency {
     mod_data: SmapiMod,
     for_mods: Vec<String>,
     required: bool,
+    local: Option<LocalSource>,
+}
+
+/// zips with mods from the svmm staging folder and the users downloads folder
+fn find_local_zips(staging_dir: &Path) -> Vec<ZipMod> {
+    let mut zips = find_zips_with_manifests(staging_dir);
+    match dirs::download_dir() {
+        Some(download_dir) => zips.extend(find_zips_with_manifests(&download_dir)),
+        None => error!("Failed to find the downloads directory"),
+    }
+    zips
+}
+
+/// looks for missing dependencies in the disabled mods, downloaded zips and the deleted bin, in that order
+async fn find_local_dependencies(missing: &[MissingDependency]) -> Result<HashMap<String, (LocalSource, String)>> {
+    let game_data = get_game_data().await?;
+    let (_, inactive_mods) = load_mods().await?;
+    let deleted_mods = load_mods_from_dir(&game_data.svmm_path.join("deleted"), false)
+        .await
+        .unwrap_or_else(|err| {
+            error!("Failed to scan deleted mods: {err}");
+            vec![]
+        });
+    let staging_dir = game_data.downloads_path.clone();
+    let zips = tokio::task::spawn_blocking(move || find_local_zips(&staging_dir)).await?;
+
+    let mut found = HashMap::new();
+    for dependency in missing {
+        let is_id = |id: &str| id.trim().eq_ignore_ascii_case(&dependency.modid);
+
+        let source = inactive_mods
+            .iter()
+            .find(|imod| is_id(&imod.manifest.unique_id))
+            .map(|imod| (LocalSource::Disabled, imod.manifest.name.clone()))
+            .or_else(|| {
+                zips.iter().find_map(|zip| {
+                    zip.manifests()
+                        .find(|manifest| is_id(&manifest.unique_id))
+                        .map(|manifest| (LocalSource::Zip(zip.path().to_path_buf()), manifest.name.clone()))
+                })
+            })
+            .or_else(|| {
+                deleted_mods
+                    .iter()
+                    .find(|imod| is_id(&imod.manifest.unique_id))
+                    .map(|imod| (LocalSource::Deleted(imod.path.clone()), imod.manifest.name.clone()))
+            });
+
+        if let Some(source) = source {
+            debug!("Found missing dependency {} locally: {source:?}", dependency.modid);
+            found.insert(dependency.modid.clone(), source);
+        }
+    }
+
+    Ok(found)
 }
 
 async fn load_missing_dependencies() -> Result<Vec<ResolvedMissingDependency>> {
     let (active_mods, _) = load_mods().await?;
     let missing = find_missing_dependencies(&active_mods);
     debug!("Missing dependencies count: {}", missing.len());
-    let mods = resolve_mods(
-        missing
-            .iter()
-            .map(|x| SmapiMod {
-                id: x.modid.to_string(),
-                ..Default::default()
-            })
-            .collect(),
-    )
-    .await?;
+
+    let local = find_local_dependencies(&missing).await?;
+
+    // only ask smapi.io about what we couldn't find on disk
+    let online: Vec<SmapiMod> = missing
+        .iter()
+        .filter(|x| !local.contains_key(&x.modid))
+        .map(|x| SmapiMod {
+            id: x.modid.to_string(),
+            ..Default::default()
+        })
+        .collect();
+    let mut mods = if online.is_empty() {
+        vec![]
+    } else {
+        let game_data = get_game_data().await?;
+        SmapiClient::from_config(&game_data.config, &game_data.svmm_path)
+            .resolve_mods(online)
+            .await?
+    };
     debug!("Resolved mods: {mods:?}");
 
+    mods.extend(local.iter().map(|(id, (_, name))| SmapiMod {
+        id: id.clone(),
+        metadata: SmapiModMetadata {
+            name: name.clone(),
+            ..Default::default()
+        },
+        ..Default::default()
+    }));
+
     let result: Vec<ResolvedMissingDependency> = mods
         .into_iter()
         .map(|resolved_mod| {
@@ -261,6 +525,7 @@ async fn load_missing_dependencies() -> Result<Vec<ResolvedMissingDependency>> {
                 .any(|missing_mod| missing_mod.modid == resolved_mod.id && missing_mod.required);
 
             ResolvedMissingDependency {
+                local: local.get(&resolved_mod.id).map(|(source, _)| source.clone()),
                 mod_data: resolved_mod,
                 for_mods: dependent_mods,
                 required: is_required,
@@ -274,6 +539,9 @@ async fn load_missing_dependencies() -> Result<Vec<ResolvedMissingDependency>> {
 impl From<&InstalledMod> for Mod {
     fn from(imod: &InstalledMod) -> Self {
         let mut rmod = Mod {
+            update: "".into(),
+            errors: imod.errors().collect::<Vec<_>>().join(", ").into(),
+            warnings: imod.warnings().collect::<Vec<_>>().join(", ").into(),
             text: imod.manifest.name.clone().into(),
             id: imod.manifest.unique_id.clone().into(),
             author: imod.manifest.author.clone().into(),
@@ -284,19 +552,27 @@ impl From<&InstalledMod> for Mod {
             nexus: "".into(),
             github: "".into(),
             moddrop: "".into(),
+            tags: "".into(),
+            category: "".into(),
+            notes: "".into(),
+            pinned: false,
+            favorite: false,
+            installed: "".into(),
+            source: "".into(),
+            selected: false,
+            group: imod.group.clone().unwrap_or_default().into(),
+            header: false,
+            collapsed: false,
         };
 
-        for entry in imod.manifest.update_keys.iter() {
-            if let Some((key, value)) = entry.splitn(2, ':').collect::<Vec<_>>().split_first() {
-                let value = value[0].split('@').next().expect("Failed splitting at @");
-                //https://community.playstarbound.com/threads/<name>.<id>/ we could add this later but i dont know if theres extra value
-                match key.to_ascii_lowercase().as_str() {
-                    "nexus" => rmod.nexus = format!("https://nexusmods.com/stardewvalley/mods/{}", value).into(),
-                    "github" => rmod.github = format!("https://github.com/{}", value).into(),
-                    "moddrop" => rmod.moddrop = format!("https://www.moddrop.com/stardew-valley/mods/{}", value).into(),
-                    _ => {
-                        debug!("Unknown update key {key} {value:?}");
-                    }
+        for (key, value) in imod.manifest.update_keys() {
+            //https://community.playstarbound.com/threads/<name>.<id>/ we could add this later but i dont know if theres extra value
+            match key.as_str() {
+                "nexus" => rmod.nexus = format!("https://nexusmods.com/stardewvalley/mods/{}", value).into(),
+                "github" => rmod.github = format!("https://github.com/{}", value).into(),
+                "moddrop" => rmod.moddrop = format!("https://www.moddrop.com/stardew-valley/mods/{}", value).into(),
+                _ => {
+                    debug!("Unknown update key {key} {value:?}");
                 }
             }
         }
@@ -316,13 +592,302 @@ fn mods_to_modelrc(mods: &[InstalledMod]) -> ModelRc<Mod> {
     generic_to_modelrc::<InstalledMod, Mod>(mods)
 }
 
+/// like `Mod::from` but with the update, errors and user metadata we know about
+fn mod_with_facts(imod: &InstalledMod, facts: &ModFacts) -> Mod {
+    let mut rmod = Mod::from(imod);
+    if let Some(version) = facts.update(&imod.manifest.unique_id) {
+        rmod.update = version.clone().into();
+    }
+    rmod.errors = facts.errors(&imod.manifest.unique_id).join(", ").into();
+    if let Some(metadata) = facts.metadata(&imod.manifest.unique_id) {
+        let desc = format_description!("[year]-[month]-[day]");
+        rmod.tags = metadata.tags.join(", ").into();
+        rmod.category = metadata.category.clone().into();
+        rmod.notes = metadata.notes.clone().into();
+        rmod.pinned = metadata.pinned;
+        rmod.favorite = metadata.favorite;
+        rmod.installed = metadata
+            .installed_at
+            .and_then(|installed_at| OffsetDateTime::from_unix_timestamp(installed_at as i64).ok())
+            .and_then(|date| date.format(desc).ok())
+            .unwrap_or_default()
+            .into();
+        if let Some(source) = &metadata.source {
+            let archive = source.archive.file_name().unwrap_or_default().to_string_lossy();
+            rmod.source = format!("{archive} ({})", &source.sha256[..source.sha256.len().min(12)]).into();
+        }
+    }
+    rmod
+}
+
+/// applies the search, filter and sort from the ui to the loaded mods
+fn refresh_mod_lists(ui: &AppWindow) {
+    let mut query = current_query(ui);
+
+    let loaded = LOADED_MODS.lock().unwrap();
+
+    // the first entry of the tag picker clears the tag filter
+    let tags = [vec![tr!("All tags")], loaded.facts.metadata.tags()].concat();
+    let tag_index = tags[1..]
+        .iter()
+        .position(|tag| tag.eq_ignore_ascii_case(&query.tag))
+        .map_or(0, |index| index + 1);
+    // the tag was removed from every mod, filtering by it would hide everything
+    if tag_index == 0 && !query.tag.is_empty() {
+        query.tag.clear();
+        ui.set_tag_filter("".into());
+    }
+
+    let (active_mods, inactive_mods): (Vec<_>, Vec<_>) = mod_filter::apply(&loaded.mods, &query, &loaded.facts)
+        .into_iter()
+        .partition(|imod| imod.active);
+
+    let row = |imod: &InstalledMod| Mod {
+        selected: loaded
+            .selected
+            .contains(&imod.manifest.unique_id.trim().to_ascii_lowercase()),
+        ..mod_with_facts(imod, &loaded.facts)
+    };
+    // grouped mods go under a header where the first mod of the group would be
+    let to_model = |mods: Vec<&InstalledMod>| {
+        let mut rows: Vec<Mod> = vec![];
+        let mut seen_groups: HashSet<&str> = HashSet::new();
+        for imod in &mods {
+            let Some(group) = imod.group.as_deref() else {
+                rows.push(row(imod));
+                continue;
+            };
+            if !seen_groups.insert(group) {
+                continue;
+            }
+            let members: Vec<&InstalledMod> = mods
+                .iter()
+                .copied()
+                .filter(|other| other.group.as_deref() == Some(group))
+                .collect();
+            let collapsed = loaded.collapsed_groups.contains(group);
+            rows.push(Mod {
+                text: group.into(),
+                description: tr!("{} mods", members.len()).into(),
+                group: group.into(),
+                active: imod.active,
+                header: true,
+                collapsed,
+                ..Default::default()
+            });
+            if !collapsed {
+                rows.extend(members.into_iter().map(row));
+            }
+        }
+        ModelRc::new(VecModel::from(rows))
+    };
+    ui.set_enabledMods(to_model(active_mods));
+    ui.set_disabledMods(to_model(inactive_mods));
+    ui.set_selected_count(loaded.selected.len() as i32);
+    ui.set_tags(generic_to_modelrc(&tags));
+    ui.set_tag_index(tag_index as i32);
+}
+
+fn current_query(ui: &AppWindow) -> ModQuery {
+    ModQuery {
+        search: ui.get_search().to_string(),
+        filter: ui.get_mod_filter().into(),
+        sort: ui.get_sort_key().into(),
+        tag: ui.get_tag_filter().to_string(),
+    }
+}
+
+fn toggle_group(ui: &AppWindow, group: &str) {
+    {
+        let mut loaded = LOADED_MODS.lock().unwrap();
+        if !loaded.collapsed_groups.remove(group) {
+            loaded.collapsed_groups.insert(group.to_owned());
+        }
+    }
+    refresh_mod_lists(ui);
+}
+
+fn select_mod(ui: &AppWindow, id: &str, selected: bool) {
+    {
+        let mut loaded = LOADED_MODS.lock().unwrap();
+        let id = id.trim().to_ascii_lowercase();
+        if selected {
+            loaded.selected.insert(id);
+        } else {
+            loaded.selected.remove(&id);
+        }
+    }
+    refresh_mod_lists(ui);
+}
+
+/// ticks or unticks one mod of a downloaded zip
+fn select_zip_mod(ui: &AppWindow, zip: &str, path: &str, selected: bool) {
+    let zips = ui.get_mods_zip();
+    for row in 0..zips.row_count() {
+        let mut mods_zip = zips.row_data(row).unwrap();
+        if mods_zip.path != zip {
+            continue;
+        }
+        for index in 0..mods_zip.mods.row_count() {
+            let mut zip_mod = mods_zip.mods.row_data(index).unwrap();
+            if zip_mod.path == path {
+                zip_mod.selected = selected;
+                mods_zip.mods.set_row_data(index, zip_mod);
+            }
+        }
+        mods_zip.selected_count = mods_zip.mods.iter().filter(|zip_mod| zip_mod.selected).count() as i32;
+        zips.set_row_data(row, mods_zip);
+    }
+}
+
+/// the folders of the ticked mods in `zip`, `None` when everything is ticked
+fn zip_selection(ui: &AppWindow, zip: &str) -> Option<Vec<PathBuf>> {
+    let mods_zip = ui.get_mods_zip().iter().find(|mods_zip| mods_zip.path == zip)?;
+    if mods_zip.selected_count as usize == mods_zip.mods.row_count() {
+        return None;
+    }
+    Some(
+        mods_zip
+            .mods
+            .iter()
+            .filter(|zip_mod| zip_mod.selected)
+            .map(|zip_mod| {
+                Path::new(zip_mod.path.as_str())
+                    .parent()
+                    .map(Path::to_path_buf)
+                    .unwrap_or_default()
+            })
+            .collect(),
+    )
+}
+
+/// selects every mod that passes the current search and filters, or clears the whole selection
+fn select_all(ui: &AppWindow, selected: bool) {
+    {
+        let mut loaded = LOADED_MODS.lock().unwrap();
+        if selected {
+            let visible: Vec<String> = mod_filter::apply(&loaded.mods, &current_query(ui), &loaded.facts)
+                .into_iter()
+                .map(|imod| imod.manifest.unique_id.trim().to_ascii_lowercase())
+                .collect();
+            loaded.selected.extend(visible);
+        } else {
+            loaded.selected.clear();
+        }
+    }
+    refresh_mod_lists(ui);
+}
+
+/// saves the tags, category and notes from the detail pane, pinned and favorite come from the buttons
+fn save_metadata(ui: &AppWindow, id: &str, pinned: bool, favorite: bool) -> Result<()> {
+    let mut loaded = LOADED_MODS.lock().unwrap();
+    let mut metadata = loaded.facts.metadata(id).cloned().unwrap_or_default();
+    metadata.tags = parse_tags(&ui.get_edit_tags());
+    metadata.category = ui.get_edit_category().trim().to_owned();
+    metadata.notes = ui.get_edit_notes().to_string();
+    metadata.pinned = pinned;
+    metadata.favorite = favorite;
+    loaded.facts.metadata.set(id, metadata);
+    loaded.facts.metadata.save()?;
+
+    if let Some(imod) = loaded.mods.iter().find(|imod| imod.has_id(id)) {
+        ui.set_active_mod(mod_with_facts(imod, &loaded.facts));
+    }
+    drop(loaded);
+
+    refresh_mod_lists(ui);
+    Ok(())
+}
+
+/// enables or disables every mod with `tag`, enabling pulls in the required dependencies as well
+async fn set_tag_active(tag: String, active: bool, handle_copy: Weak<AppWindow>) -> Result<()> {
+    let (active_mods, inactive_mods) = load_mods().await?;
+    let tagged: Vec<String> = {
+        let loaded = LOADED_MODS.lock().unwrap();
+        let has_tag = |imod: &&InstalledMod| {
+            loaded
+                .facts
+                .metadata(&imod.manifest.unique_id)
+                .is_some_and(|metadata| metadata.has_tag(&tag))
+        };
+        if active {
+            let mut ids: Vec<String> = vec![];
+            for imod in inactive_mods.iter().filter(has_tag) {
+                let dependencies = disabled_required_dependencies(imod, &active_mods, &inactive_mods);
+                for id in dependencies
+                    .iter()
+                    .map(|dependency| &dependency.manifest.unique_id)
+                    .chain([&imod.manifest.unique_id])
+                {
+                    if !ids.contains(id) {
+                        ids.push(id.clone());
+                    }
+                }
+            }
+            ids
+        } else {
+            active_mods
+                .iter()
+                .filter(has_tag)
+                .map(|imod| imod.manifest.unique_id.clone())
+                .collect()
+        }
+    };
+
+    info!(
+        "{} {} mods tagged {tag}",
+        if active { "Enabling" } else { "Disabling" },
+        tagged.len()
+    );
+    for id in tagged {
+        switch_mod(&id).await?;
+    }
+
+    reload(handle_copy).await
+}
+
+async fn check_updates(handle_copy: Weak<AppWindow>) -> Result<()> {
+    let game_data = get_game_data().await?;
+
+    let mods: Vec<SmapiMod> = LOADED_MODS
+        .lock()
+        .unwrap()
+        .mods
+        .iter()
+        .map(|imod| SmapiMod {
+            id: imod.manifest.unique_id.clone(),
+            installed_version: Some(imod.manifest.version.clone()),
+            update_keys: imod.manifest.update_keys.clone(),
+            ..Default::default()
+        })
+        .collect();
+
+    let resolved = SmapiClient::from_config(&game_data.config, &game_data.svmm_path)
+        .check_updates(mods)
+        .await?;
+    let updates: HashMap<String, String> = resolved
+        .into_iter()
+        .filter_map(|imod| Some((imod.id.trim().to_ascii_lowercase(), imod.suggested_update?.version)))
+        .collect();
+    info!("{} mods have updates", updates.len());
+
+    LOADED_MODS.lock().unwrap().facts.updates = updates;
+
+    slint::invoke_from_event_loop(move || {
+        refresh_mod_lists(&handle_copy.unwrap());
+    })
+    .unwrap();
+
+    Ok(())
+}
+
 async fn find_mod<A: AsRef<str>>(id: A) -> Result<InstalledMod> {
     let id = id.as_ref();
 
     let (active_mods, inactive_mods) = load_mods().await?;
 
-    let active_mod = active_mods.iter().find(|imod| imod.manifest.unique_id == id);
-    let inactive_mod = inactive_mods.iter().find(|imod| imod.manifest.unique_id == id);
+    let active_mod = active_mods.iter().find(|imod| imod.has_id(id));
+    let inactive_mod = inactive_mods.iter().find(|imod| imod.has_id(id));
 
     match (active_mod, inactive_mod) {
         (Some(active_mod), _) => Ok(active_mod.clone()),
@@ -339,9 +904,10 @@ struct MissingDependency {
 }
 
 fn find_missing_dependencies(mods: &[InstalledMod]) -> Vec<MissingDependency> {
+    // smapi matches unique ids case insensitively
     let installed_ids: HashSet<_> = mods
         .iter()
-        .map(|imod| imod.manifest.unique_id.trim().to_owned())
+        .map(|imod| imod.manifest.unique_id.trim().to_ascii_lowercase())
         .collect();
 
     let mut missing_deps: HashMap<String, MissingDependency> = HashMap::new();
@@ -349,9 +915,10 @@ fn find_missing_dependencies(mods: &[InstalledMod]) -> Vec<MissingDependency> {
     for imod in mods.iter() {
         for dependency in &imod.manifest.dependencies {
             let dep_id = dependency.unique_id.trim().to_owned();
+            let key = dep_id.to_ascii_lowercase();
 
-            if !installed_ids.contains(&dep_id) {
-                let entry = missing_deps.entry(dep_id.clone()).or_insert_with(|| MissingDependency {
+            if !installed_ids.contains(&key) {
+                let entry = missing_deps.entry(key).or_insert_with(|| MissingDependency {
                     modid: dep_id.clone(),
                     for_mods: vec![],
                     required: dependency.required,
@@ -371,6 +938,50 @@ fn find_missing_dependencies(mods: &[InstalledMod]) -> Vec<MissingDependency> {
     missing_deps.values().cloned().collect()
 }
 
+/// disabled mods that have to be enabled alongside `imod`, including the dependencies of those dependencies
+fn disabled_required_dependencies<'a>(
+    imod: &InstalledMod,
+    active_mods: &[InstalledMod],
+    inactive_mods: &'a [InstalledMod],
+) -> Vec<&'a InstalledMod> {
+    let mut result: Vec<&InstalledMod> = vec![];
+    let mut queue: Vec<String> = imod.manifest.required_dependencies().map(str::to_owned).collect();
+
+    while let Some(id) = queue.pop() {
+        let is_id = |other: &&InstalledMod| other.has_id(&id);
+        if active_mods.iter().any(|other| is_id(&other)) || result.iter().any(is_id) {
+            continue;
+        }
+        if let Some(dependency) = inactive_mods.iter().find(is_id) {
+            queue.extend(dependency.manifest.required_dependencies().map(str::to_owned));
+            result.push(dependency);
+        }
+    }
+
+    result
+}
+
+/// active mods that stop working when `id` gets disabled, including mods that depend on those
+fn active_dependents<'a>(id: &str, active_mods: &'a [InstalledMod]) -> Vec<&'a InstalledMod> {
+    let mut result: Vec<&InstalledMod> = vec![];
+    let mut queue = vec![id.trim().to_owned()];
+
+    while let Some(id) = queue.pop() {
+        for imod in active_mods {
+            let depends_on_id = imod
+                .manifest
+                .required_dependencies()
+                .any(|dependency| dependency.eq_ignore_ascii_case(&id));
+            if depends_on_id && !result.iter().any(|other| other.path == imod.path) {
+                queue.push(imod.manifest.unique_id.trim().to_owned());
+                result.push(imod);
+            }
+        }
+    }
+
+    result
+}
+
 impl From<&ResolvedMissingDependency> for SmapiApiMod {
     fn from(smapi_mod: &ResolvedMissingDependency) -> Self {
         SmapiApiMod {
@@ -379,11 +990,77 @@ impl From<&ResolvedMissingDependency> for SmapiApiMod {
             url: smapi_mod.mod_data.metadata.main.url.clone().into(),
             required_for: generic_to_modelrc(&smapi_mod.for_mods),
             required: smapi_mod.required,
+            local_kind: match smapi_mod.local {
+                Some(LocalSource::Disabled) => "disabled",
+                Some(LocalSource::Zip(_)) => "zip",
+                Some(LocalSource::Deleted(_)) => "deleted",
+                None => "",
+            }
+            .into(),
+            local_path: match &smapi_mod.local {
+                Some(LocalSource::Zip(path) | LocalSource::Deleted(path)) => path.to_string_lossy().to_string().into(),
+                _ => "".into(),
+            },
+        }
+    }
+}
+
+impl From<&NexusFile> for NexusApiFile {
+    fn from(file: &NexusFile) -> Self {
+        let desc = format_description!("[year repr:last_two]-[month]-[day] [hour]:[minute]");
+
+        NexusApiFile {
+            name: file.name.clone().into(),
+            version: file.version.clone().into(),
+            category: file.category_name.clone().unwrap_or_default().into(),
+            size: format!("{:.1} MB", file.size_kb as f64 / 1024.0).into(),
+            uploaded: OffsetDateTime::from_unix_timestamp(file.uploaded_timestamp)
+                .ok()
+                .and_then(|date| date.format(desc).ok())
+                .unwrap_or_default()
+                .into(),
+        }
+    }
+}
+
+impl From<&NexusModDetails> for NexusApiMod {
+    fn from(details: &NexusModDetails) -> Self {
+        NexusApiMod {
+            loaded: true,
+            name: details.info.name.clone().into(),
+            summary: details.info.summary.clone().into(),
+            version: details.info.version.clone().into(),
+            author: details.info.author.clone().into(),
+            endorsements: details.info.endorsement_count as i32,
+            files: generic_to_modelrc(&details.files),
         }
     }
 }
 
+/// cycles in the dependency graph as readable strings like `A -> B -> A`
+fn describe_cycles(graph: &DependencyGraph) -> Vec<String> {
+    graph
+        .cycles()
+        .into_iter()
+        .map(|cycle| {
+            let mut names: Vec<&str> = cycle.iter().map(|&node| graph.nodes[node].name.as_str()).collect();
+            names.push(names[0]);
+            names.join(" -> ")
+        })
+        .collect()
+}
+
 async fn set_missing_mods(handle_copy: Weak<AppWindow>) -> Result<()> {
+    let (active_mods, inactive_mods) = load_mods().await?;
+    let cycles = describe_cycles(&DependencyGraph::new(&[active_mods, inactive_mods].concat()));
+    let handle_cycles = handle_copy.clone();
+    slint::invoke_from_event_loop(move || {
+        handle_cycles
+            .unwrap()
+            .set_dependency_cycles(generic_to_modelrc(&cycles));
+    })
+    .unwrap();
+
     let mut mods = load_missing_dependencies().await?;
 
     mods.sort_by(|a, b| (b.required as usize, &b.mod_data.id).cmp(&(a.required as usize, &a.mod_data.id)));
@@ -400,25 +1077,159 @@ async fn set_missing_mods(handle_copy: Weak<AppWindow>) -> Result<()> {
     Ok(())
 }
 
-async fn remove_mod<A: AsRef<str>>(id: A) -> Result<()> {
-    let imod = find_mod(id).await?;
-
+/// writes the dependency graph of every installed mod into the svmm folder and opens it
+async fn export_dependency_graph(format: String) -> Result<()> {
     let game_data = get_game_data().await?;
+    let (active_mods, inactive_mods) = load_mods().await?;
+    let graph = DependencyGraph::new(&[active_mods, inactive_mods].concat());
 
-    let deleted_folder = game_data.svmm_path.join("deleted");
+    let (file_name, content) = match format.as_str() {
+        "dot" => ("dependency-graph.dot", graph.to_dot()),
+        "mermaid" => ("dependency-graph.mmd", graph.to_mermaid()),
+        _ => {
+            let mut lines: Vec<String> = graph
+                .topological_order()
+                .into_iter()
+                .map(|node| format!("{} ({})", graph.nodes[node].name, graph.nodes[node].id))
+                .collect();
+            lines.extend(describe_cycles(&graph).into_iter().map(|cycle| tr!("cycle: {}", cycle)));
+            lines.extend(
+                graph
+                    .unresolved()
+                    .map(|edge| tr!("missing: {} needed by {}", edge.target_id, graph.nodes[edge.from].id)),
+            );
+            ("dependency-order.txt", lines.join("\n"))
+        }
+    };
+
+    let path = game_data.svmm_path.join(file_name);
+    write(&path, content)?;
+    info!("Exported dependency graph to {path:?}");
+    opener::open(&path)?;
+
+    Ok(())
+}
+
+/// removes the folders between `path` and `root` that were left empty by moving a grouped mod out
+fn remove_empty_parents(path: &Path, root: &Path) -> Result<()> {
+    for parent in path.ancestors().skip(1) {
+        if parent == root || !parent.starts_with(root) || read_dir(parent)?.next().is_some() {
+            break;
+        }
+        debug!("Removing empty folder {parent:?}");
+        remove_dir(parent)?;
+    }
+    Ok(())
+}
+
+/// moves `from` to `to`, merging into `to` when a folder with that name is already there
+fn move_merging(from: &Path, to: &Path) -> Result<()> {
+    if !to.try_exists()? {
+        if let Some(parent_dir) = to.parent() {
+            create_dir_all(parent_dir)?;
+        }
+        return rename_logged(from, to);
+    }
+    if !from.is_dir() || !to.is_dir() {
+        Err(color_eyre::eyre::eyre!(tr!("{} already exists", to.display())))?;
+    }
+    for entry in read_dir(from)? {
+        let entry = entry?;
+        move_merging(&entry.path(), &to.join(entry.file_name()))?;
+    }
+    remove_dir(from)?;
+    Ok(())
+}
+
+async fn remove_mod<A: AsRef<str>>(id: A) -> Result<()> {
+    remove_installed(&find_mod(id).await?).await
+}
 
+/// moves `path` into the `deleted` bin under a name that doesn't clash, returns where it ended up
+fn move_to_deleted(path: &Path, deleted_folder: &Path) -> Result<PathBuf> {
     let location = deleted_folder.join(format!(
         "{}-{}",
-        imod.path.file_name().unwrap().to_string_lossy(),
+        path.file_name().unwrap().to_string_lossy(),
         SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis()
     ));
+    rename_logged(path, &location)?;
+    Ok(location)
+}
+
+/// moves an already scanned mod into the deleted bin
+async fn remove_installed(imod: &InstalledMod) -> Result<()> {
+    let game_data = get_game_data().await?;
+
+    move_to_deleted(&imod.path, &game_data.svmm_path.join("deleted"))?;
+
+    let root = if imod.path.starts_with(&game_data.mods_path) {
+        game_data.mods_path.clone()
+    } else {
+        game_data
+            .profile_path
+            .join(get_active_profile().await?)
+            .join("disabled")
+    };
+    remove_empty_parents(&imod.path, &root)?;
+
+    Ok(())
+}
+
+/// moves a mod from the deleted bin back into the mods folder
+async fn restore_deleted(path: PathBuf) -> Result<()> {
+    let game_data = get_game_data().await?;
+    let deleted_folder = game_data.svmm_path.join("deleted");
+
+    // the mod might live in a group folder, restore the whole entry
+    let entry = path
+        .strip_prefix(&deleted_folder)?
+        .components()
+        .next()
+        .ok_or(color_eyre::eyre::eyre!(tr!("Not a deleted mod: {}", path.display())))?;
+    let entry_name = entry.as_os_str().to_string_lossy().to_string();
+
+    // remove_mod appends -<millis> to the folder name
+    let name = match entry_name.rsplit_once('-') {
+        Some((name, millis)) if !millis.is_empty() && millis.chars().all(|c| c.is_ascii_digit()) => name,
+        _ => entry_name.as_str(),
+    };
+
+    let target = game_data.mods_path.join(name);
+    if target.try_exists()? {
+        Err(color_eyre::eyre::eyre!(tr!("{} already exists", target.display())))?;
+    }
 
-    rename(&imod.path, location)?;
+    info!("Restoring {entry_name} from deleted mods");
+    rename_logged(deleted_folder.join(&entry_name), target)?;
 
     Ok(())
 }
 
+async fn install_local_dependency(id: String, kind: String, path: String, handle_copy: Weak<AppWindow>) -> Result<()> {
+    match kind.as_str() {
+        // toggle_mod also pulls in the dependencies of the dependency
+        "disabled" => toggle_mod(id, handle_copy.clone()).await?,
+        "zip" => {
+            show_install_notices(install_mods(path, None, false).await?, handle_copy.clone());
+            reload(handle_copy.clone()).await?;
+        }
+        "deleted" => {
+            restore_deleted(path.into()).await?;
+            reload(handle_copy.clone()).await?;
+        }
+        _ => Err(color_eyre::eyre::eyre!("Unknown local source {kind}"))?,
+    }
+
+    set_missing_mods(handle_copy).await
+}
+
 async fn switch_mod<A: AsRef<str>>(id: A) -> Result<()> {
+    let (active_mods, inactive_mods) = load_mods().await?;
+    switch_mod_in(id.as_ref(), &active_mods, &inactive_mods).await
+}
+
+/// [`switch_mod`] on mods that were already scanned, moving many mods shouldn't rescan the folders for every one
+async fn switch_mod_in(id: &str, active_mods: &[InstalledMod], inactive_mods: &[InstalledMod]) -> Result<()> {
     let game_data = get_game_data().await?;
 
     let profile = get_active_profile().await?;
@@ -426,35 +1237,43 @@ async fn switch_mod<A: AsRef<str>>(id: A) -> Result<()> {
     let disabled_mods = game_data.profile_path.join(profile).join("disabled");
     let active_mods_dir = &game_data.mods_path;
 
-    let inactive_mods = load_mods_from_dir(&disabled_mods, false).await?;
-    let active_mods = load_mods_from_dir(active_mods_dir, true).await?;
-
-    let id = id.as_ref();
-
-    let active_mod = active_mods.iter().find(|imod| imod.manifest.unique_id == id);
-    let inactive_mod = inactive_mods.iter().find(|imod| imod.manifest.unique_id == id);
+    let active_mod = active_mods.iter().find(|imod| imod.has_id(id));
+    let inactive_mod = inactive_mods.iter().find(|imod| imod.has_id(id));
 
     match (active_mod, inactive_mod) {
         (Some(active_mod), None) => {
             info!("Making mod {id} inactive");
-            // Move from active mods to inactive mods
             let relative_path = active_mod.path.strip_prefix(active_mods_dir)?;
-            let target_path = disabled_mods.join(relative_path);
+            let target_path = match game_data.config.disable_mode {
+                // Move from active mods to inactive mods
+                DisableMode::Move => disabled_mods.join(relative_path),
+                DisableMode::DotPrefix => {
+                    let name = active_mod.path.file_name().unwrap().to_string_lossy();
+                    active_mod.path.with_file_name(format!(".{name}"))
+                }
+            };
             if let Some(parent_dir) = target_path.parent() {
                 create_dir_all(parent_dir)?; // Ensure parent directories exist
             }
-            rename(&active_mod.path, target_path)?;
+            rename_logged(&active_mod.path, target_path)?;
+            remove_empty_parents(&active_mod.path, active_mods_dir)?;
             info!("Moved mod to inactive: {}", id);
         }
         (None, Some(inactive_mod)) => {
             info!("Making mod {id} active");
-            // Move from inactive mods to active mods
-            let relative_path = inactive_mod.path.strip_prefix(&disabled_mods)?;
-            let target_path = active_mods_dir.join(relative_path);
+            // Move from inactive mods to active mods, or drop the dots when it was disabled in place
+            let from_root = if inactive_mod.path.starts_with(active_mods_dir) {
+                active_mods_dir
+            } else {
+                &disabled_mods
+            };
+            let relative_path = inactive_mod.path.strip_prefix(from_root)?;
+            let target_path = active_mods_dir.join(unhide(relative_path));
             if let Some(parent_dir) = target_path.parent() {
                 create_dir_all(parent_dir)?; // Ensure parent directories exist
             }
-            rename(&inactive_mod.path, target_path)?;
+            rename_logged(&inactive_mod.path, target_path)?;
+            remove_empty_parents(&inactive_mod.path, from_root)?;
             info!("Moved mod to active: {}", id);
         }
         (Some(_), Some(_)) => {
@@ -473,9 +1292,324 @@ async fn switch_mod<A: AsRef<str>>(id: A) -> Result<()> {
     Ok(())
 }
 
+/// enables a mod together with its disabled dependencies, or asks before disabling a mod other mods need
+async fn toggle_mod(id: String, handle_copy: Weak<AppWindow>) -> Result<()> {
+    let (active_mods, inactive_mods) = load_mods().await?;
+
+    if let Some(imod) = active_mods.iter().find(|imod| imod.has_id(&id)) {
+        let dependents = active_dependents(&imod.manifest.unique_id, &active_mods);
+        if !dependents.is_empty() {
+            info!(
+                "{} active mods depend on {id}, asking before disabling",
+                dependents.len()
+            );
+            let name = imod.manifest.name.clone();
+            let dependents: Vec<String> = dependents.iter().map(|imod| imod.manifest.name.clone()).collect();
+            slint::invoke_from_event_loop(move || {
+                let ui_weak = handle_copy.unwrap();
+                let warning = DisableWarning {
+                    id: id.into(),
+                    name: name.into(),
+                    dependents: generic_to_modelrc(&dependents),
+                };
+                ui_weak.set_disable_warning(warning);
+                ui_weak.set_disable_warning_open(true);
+            })
+            .unwrap();
+            return Ok(());
+        }
+        switch_mod(&id).await?;
+    } else if let Some(imod) = inactive_mods.iter().find(|imod| imod.has_id(&id)) {
+        let dependencies = disabled_required_dependencies(imod, &active_mods, &inactive_mods);
+        switch_mod(&id).await?;
+        for dependency in dependencies {
+            info!(
+                "Enabling required dependency {} for {id}",
+                dependency.manifest.unique_id
+            );
+            switch_mod(&dependency.manifest.unique_id).await?;
+        }
+    } else {
+        Err(io::Error::new(io::ErrorKind::NotFound, "Mod not found."))?;
+    }
+
+    reload(handle_copy).await
+}
+
+async fn disable_mod(id: String, with_dependents: bool, handle_copy: Weak<AppWindow>) -> Result<()> {
+    if with_dependents {
+        let (active_mods, _) = load_mods().await?;
+        for dependent in active_dependents(&id, &active_mods) {
+            info!("Disabling dependent mod {} of {id}", dependent.manifest.unique_id);
+            switch_mod(&dependent.manifest.unique_id).await?;
+        }
+    }
+    switch_mod(&id).await?;
+
+    reload(handle_copy).await
+}
+
+#[derive(Clone, Debug)]
+enum BulkAction {
+    Enable,
+    Disable,
+    Delete,
+    MoveToProfile(String),
+}
+
+impl BulkAction {
+    /// the action names the bulk bar in `modlist.slint` uses
+    fn parse(action: &str, profile: String) -> Result<Self> {
+        match action {
+            "enable" => Ok(BulkAction::Enable),
+            "disable" => Ok(BulkAction::Disable),
+            "delete" => Ok(BulkAction::Delete),
+            "move" => Ok(BulkAction::MoveToProfile(profile)),
+            _ => Err(color_eyre::eyre::eyre!("Unknown bulk action {action}")),
+        }
+    }
+}
+
+/// what happened to every mod in a bulk operation
+#[derive(Clone, Debug, Default)]
+struct BulkReport {
+    done: Vec<String>,
+    skipped: Vec<String>,
+    failed: Vec<(String, String)>,
+    warnings: Vec<String>,
+}
+
+impl BulkReport {
+    fn summary(&self, action: &BulkAction) -> String {
+        let done = self.done.len();
+        let headline = match action {
+            BulkAction::Enable => tr!("Enabled {} mods", done),
+            BulkAction::Disable => tr!("Disabled {} mods", done),
+            BulkAction::Delete => tr!("Deleted {} mods", done),
+            BulkAction::MoveToProfile(profile) => tr!("Moved {} mods to {}", done, profile),
+        };
+        let mut lines = vec![headline];
+        if !self.skipped.is_empty() {
+            lines.push(tr!("Skipped {}: {}", self.skipped.len(), self.skipped.join(", ")));
+        }
+        for (id, err) in &self.failed {
+            lines.push(tr!("Failed {}: {}", id, err));
+        }
+        lines.extend(self.warnings.iter().cloned());
+        lines.join("\n")
+    }
+}
+
+/// moves a mod into another profile, keeping it enabled or disabled
+async fn move_mod_to_profile(imod: &InstalledMod, profile: &str) -> Result<()> {
+    let game_data = get_game_data().await?;
+    let active_profile = get_active_profile().await?;
+
+    let from_root = if imod.path.starts_with(&game_data.mods_path) {
+        game_data.mods_path.clone()
+    } else {
+        game_data.profile_path.join(&active_profile).join("disabled")
+    };
+    let folder = if imod.active { "enabled" } else { "disabled" };
+    let target = game_data
+        .profile_path
+        .join(profile)
+        .join(folder)
+        .join(unhide(imod.path.strip_prefix(&from_root)?));
+    if target.try_exists()? {
+        Err(color_eyre::eyre::eyre!(tr!("{} already exists", target.display())))?;
+    }
+    if let Some(parent_dir) = target.parent() {
+        create_dir_all(parent_dir)?;
+    }
+    rename_logged(&imod.path, target)?;
+    remove_empty_parents(&imod.path, &from_root)?;
+
+    Ok(())
+}
+
+/// moves a whole group folder to the other side, enabling a group also enables the dependencies of its mods
+async fn switch_group(group: String, active: bool, handle_copy: Weak<AppWindow>) -> Result<()> {
+    let game_data = get_game_data().await?;
+    let disabled_dir = game_data
+        .profile_path
+        .join(get_active_profile().await?)
+        .join("disabled");
+    let mods_path = &game_data.mods_path;
+    let hidden_group = mods_path.join(format!(".{group}"));
+
+    let mut dependencies: Vec<String> = vec![];
+    if !active {
+        let (active_mods, inactive_mods) = load_mods().await?;
+        for imod in inactive_mods.iter().filter(|imod| imod.group.as_ref() == Some(&group)) {
+            for dependency in disabled_required_dependencies(imod, &active_mods, &inactive_mods) {
+                if dependency.group.as_ref() != Some(&group) && !dependencies.contains(&dependency.manifest.unique_id) {
+                    dependencies.push(dependency.manifest.unique_id.clone());
+                }
+            }
+        }
+    }
+
+    info!("{} group {group}", if active { "Disabling" } else { "Enabling" });
+    match (active, game_data.config.disable_mode) {
+        (true, DisableMode::Move) => move_merging(&mods_path.join(&group), &disabled_dir.join(&group))?,
+        (true, DisableMode::DotPrefix) => move_merging(&mods_path.join(&group), &hidden_group)?,
+        (false, _) => {
+            // the group can be disabled both ways at once
+            for from in [hidden_group, disabled_dir.join(&group)] {
+                if from.try_exists()? {
+                    move_merging(&from, &mods_path.join(&group))?;
+                }
+            }
+        }
+    }
+    for id in dependencies {
+        info!("Enabling required dependency {id} for group {group}");
+        switch_mod(&id).await?;
+    }
+
+    reload(handle_copy).await
+}
+
+/// runs `action` on every selected mod, reloads once at the end and shows a single report. Deleting asks first when a
+/// save of the active profile still uses one of the mods, like deleting a single mod does
+async fn bulk_action(action: BulkAction, confirmed: bool, handle_copy: Weak<AppWindow>) -> Result<()> {
+    if let BulkAction::MoveToProfile(profile) = &action {
+        if *profile == get_active_profile().await? {
+            Err(color_eyre::eyre::eyre!(tr!("The mods are already in {}", profile)))?;
+        }
+    }
+
+    let (active_mods, inactive_mods) = load_mods().await?;
+
+    if matches!(action, BulkAction::Delete) && !confirmed {
+        let selected = LOADED_MODS.lock().unwrap().selected.clone();
+        let dropped = active_mods
+            .iter()
+            .chain(&inactive_mods)
+            .filter(|imod| selected.contains(&imod.manifest.unique_id.trim().to_ascii_lowercase()))
+            .map(|imod| (imod.manifest.unique_id.clone(), imod.manifest.name.clone()))
+            .collect();
+        let references = save_references(&get_active_profile().await?, dropped).await?;
+        if !references.is_empty() {
+            let title = tr!("These saves still use the selected mods:");
+            show_save_warning("bulk-delete", String::new(), title, references, handle_copy);
+            return Ok(());
+        }
+    }
+
+    let selected = std::mem::take(&mut LOADED_MODS.lock().unwrap().selected);
+    let is_selected = |imod: &&InstalledMod| selected.contains(&imod.manifest.unique_id.trim().to_ascii_lowercase());
+
+    let mut report = BulkReport::default();
+    match &action {
+        BulkAction::Enable => {
+            let mut ids: Vec<String> = vec![];
+            for imod in inactive_mods.iter().filter(is_selected) {
+                for dependency in disabled_required_dependencies(imod, &active_mods, &inactive_mods) {
+                    if !ids.contains(&dependency.manifest.unique_id) {
+                        report.warnings.push(tr!(
+                            "Also enabled {} for {}",
+                            dependency.manifest.name,
+                            imod.manifest.name
+                        ));
+                        ids.push(dependency.manifest.unique_id.clone());
+                    }
+                }
+                if !ids.contains(&imod.manifest.unique_id) {
+                    ids.push(imod.manifest.unique_id.clone());
+                }
+            }
+            report.skipped = active_mods
+                .iter()
+                .filter(is_selected)
+                .map(|imod| imod.manifest.name.clone())
+                .collect();
+            for id in ids {
+                match switch_mod_in(&id, &active_mods, &inactive_mods).await {
+                    Ok(_) => report.done.push(id),
+                    Err(err) => report.failed.push((id, err.to_string())),
+                }
+            }
+        }
+        BulkAction::Disable => {
+            let disabling: Vec<&InstalledMod> = active_mods.iter().filter(is_selected).collect();
+            for imod in &disabling {
+                let broken: Vec<String> = active_dependents(&imod.manifest.unique_id, &active_mods)
+                    .into_iter()
+                    .filter(|dependent| !disabling.iter().any(|other| other.path == dependent.path))
+                    .map(|dependent| dependent.manifest.name.clone())
+                    .collect();
+                if !broken.is_empty() {
+                    report
+                        .warnings
+                        .push(tr!("{} is still needed by {}", imod.manifest.name, broken.join(", ")));
+                }
+            }
+            report.skipped = inactive_mods
+                .iter()
+                .filter(is_selected)
+                .map(|imod| imod.manifest.name.clone())
+                .collect();
+            for imod in disabling {
+                match switch_mod_in(&imod.manifest.unique_id, &active_mods, &inactive_mods).await {
+                    Ok(_) => report.done.push(imod.manifest.unique_id.clone()),
+                    Err(err) => report.failed.push((imod.manifest.unique_id.clone(), err.to_string())),
+                }
+            }
+        }
+        BulkAction::Delete => {
+            for imod in active_mods.iter().chain(&inactive_mods).filter(is_selected) {
+                match remove_installed(imod).await {
+                    Ok(_) => report.done.push(imod.manifest.unique_id.clone()),
+                    Err(err) => report.failed.push((imod.manifest.unique_id.clone(), err.to_string())),
+                }
+            }
+        }
+        BulkAction::MoveToProfile(profile) => {
+            for imod in active_mods.iter().chain(&inactive_mods).filter(is_selected) {
+                match move_mod_to_profile(imod, profile).await {
+                    Ok(_) => report.done.push(imod.manifest.unique_id.clone()),
+                    Err(err) => report.failed.push((imod.manifest.unique_id.clone(), err.to_string())),
+                }
+            }
+        }
+    }
+
+    let summary = report.summary(&action);
+    info!("Bulk action {action:?}: {summary}");
+
+    let handle_report = handle_copy.clone();
+    slint::invoke_from_event_loop(move || {
+        handle_report.unwrap().set_bulk_report(summary.into());
+    })
+    .unwrap();
+
+    reload(handle_copy).await
+}
+
 async fn reload(handle_copy: Weak<AppWindow>) -> Result<()> {
     let then = Instant::now();
     let (active_mods, inactive_mods) = load_mods().await?;
+    let mods = [active_mods, inactive_mods].concat();
+
+    let mut metadata = MetadataStore::load(get_game_data().await?.svmm_path.join("metadata.ron"));
+    if metadata.record_installs(&mods) {
+        metadata.save()?;
+    }
+
+    {
+        let mut loaded = LOADED_MODS.lock().unwrap();
+        let updates = std::mem::take(&mut loaded.facts.updates);
+        loaded.facts = ModFacts::new(&mods, updates, metadata);
+        // deleted mods can't stay selected
+        let ids: HashSet<String> = mods
+            .iter()
+            .map(|imod| imod.manifest.unique_id.trim().to_ascii_lowercase())
+            .collect();
+        loaded.selected.retain(|id| ids.contains(id));
+        loaded.mods = mods;
+    }
 
     let profiles = get_profiles_names().await?;
     let profile = get_active_profile().await?;
@@ -489,20 +1623,33 @@ async fn reload(handle_copy: Weak<AppWindow>) -> Result<()> {
 
         ui_weak.set_profile(profile.into());
 
-        ui_weak.set_enabledMods(mods_to_modelrc(&active_mods));
-        ui_weak.set_disabledMods(mods_to_modelrc(&inactive_mods));
+        refresh_mod_lists(&ui_weak);
 
         debug!("Reloading took: {}ms", then.elapsed().as_millis())
     })
     .unwrap();
 
+    let staging_dir = get_game_data().await?.downloads_path.clone();
+    let installed_mods = LOADED_MODS.lock().unwrap().mods.clone();
+
     // dont block the ui for *too* long
     tokio::task::spawn_blocking(move || {
-        let base_dir = dirs::download_dir().expect("Failed to find the downloads directory");
-        let zips_with_manifests = find_zips_with_manifests(&base_dir);
+        let zips_with_manifests = find_local_zips(&staging_dir);
+        let statuses: Vec<_> = zips_with_manifests
+            .iter()
+            .map(|zip| zip.status(&installed_mods))
+            .collect();
         slint::invoke_from_event_loop(move || {
             let ui_weak = copy2.unwrap();
-            ui_weak.set_mods_zip(generic_to_modelrc::<ZipMod, ModsZip>(&zips_with_manifests));
+            let zips: Vec<ModsZip> = zips_with_manifests
+                .iter()
+                .zip(statuses)
+                .map(|(zip, status)| ModsZip {
+                    status: status.as_str().into(),
+                    ..ModsZip::from(zip)
+                })
+                .collect();
+            ui_weak.set_mods_zip(ModelRc::new(VecModel::from(zips)));
         })
     });
 
@@ -510,16 +1657,134 @@ async fn reload(handle_copy: Weak<AppWindow>) -> Result<()> {
     Ok(())
 }
 
+fn load_history(game_data: &GameData) -> History {
+    History::load(
+        game_data.svmm_path.join("history.ron"),
+        game_data.config.history_size.unwrap_or(50),
+    )
+}
+
+async fn set_history_labels(handle_copy: Weak<AppWindow>) -> Result<()> {
+    let history = load_history(get_game_data().await?);
+    let label = |operations: &[history::Operation]| {
+        operations
+            .last()
+            .map(|operation| operation.description.clone())
+            .unwrap_or_default()
+    };
+    let (undo, redo) = (label(&history.undo), label(&history.redo));
+
+    slint::invoke_from_event_loop(move || {
+        let ui_weak = handle_copy.unwrap();
+        ui_weak.set_undo_label(undo.into());
+        ui_weak.set_redo_label(redo.into());
+    })
+    .unwrap();
+
+    Ok(())
+}
+
+/// runs `future` and records every move it made as one undoable operation, even when it fails halfway
+async fn recorded<T>(
+    description: String,
+    handle_copy: Weak<AppWindow>,
+    future: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    let (result, changes) = history::recording(future).await;
+    load_history(get_game_data().await?).commit(description, changes)?;
+    set_history_labels(handle_copy).await?;
+    result
+}
+
+/// the name of a scanned mod for history labels, falling back to its id
+fn find_loaded_name(id: &str) -> String {
+    let loaded = LOADED_MODS.lock().unwrap();
+    loaded
+        .mods
+        .iter()
+        .find(|imod| imod.has_id(id))
+        .map_or_else(|| id.to_owned(), |imod| imod.manifest.name.clone())
+}
+
+/// `"Enable <name>"` or `"Disable <name>"` depending on where the mod is now
+fn describe_toggle(id: &str) -> String {
+    let loaded = LOADED_MODS.lock().unwrap();
+    match loaded.mods.iter().find(|imod| imod.has_id(id)) {
+        Some(imod) if imod.active => tr!("Disable {}", imod.manifest.name),
+        Some(imod) => tr!("Enable {}", imod.manifest.name),
+        None => tr!("Toggle {}", id),
+    }
+}
+
+async fn undo(redo: bool, handle_copy: Weak<AppWindow>) -> Result<()> {
+    let mut history = load_history(get_game_data().await?);
+    let result = if redo { history.redo() } else { history.undo() };
+
+    let status = match &result {
+        Ok(Some(description)) if redo => tr!("Redid {}", description),
+        Ok(Some(description)) => tr!("Undid {}", description),
+        Ok(None) if redo => tr!("Nothing to redo"),
+        Ok(None) => tr!("Nothing to undo"),
+        Err(err) => tr!("Failed: {}", err),
+    };
+    let handle_status = handle_copy.clone();
+    slint::invoke_from_event_loop(move || {
+        handle_status.unwrap().set_bulk_report(status.into());
+    })
+    .unwrap();
+
+    set_history_labels(handle_copy.clone()).await?;
+    reload(handle_copy).await?;
+    result.map(|_| ())
+}
+
+/// `--undo`, `--redo` and `--history` work without opening the window, returns whether one of them ran
+async fn run_history_command() -> Result<bool> {
+    let game_data = get_game_data().await?;
+    let mut history = load_history(game_data);
+
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--undo" => match history.undo()? {
+                Some(description) => println!("{}", tr!("Undid {}", description)),
+                None => println!("{}", tr!("Nothing to undo")),
+            },
+            "--redo" => match history.redo()? {
+                Some(description) => println!("{}", tr!("Redid {}", description)),
+                None => println!("{}", tr!("Nothing to redo")),
+            },
+            "--history" => {
+                let desc = format_description!("[year]-[month]-[day] [hour]:[minute]");
+                for operation in history.undo.iter().rev() {
+                    let at = OffsetDateTime::from_unix_timestamp(operation.at as i64)
+                        .ok()
+                        .and_then(|date| date.format(desc).ok())
+                        .unwrap_or_default();
+                    println!("{at}  {}", operation.description);
+                }
+            }
+            _ => continue,
+        }
+        return Ok(true);
+    }
+
+    Ok(false)
+}
+
 async fn load_mods() -> Result<(Vec<InstalledMod>, Vec<InstalledMod>)> {
     let game_data = get_game_data().await?;
 
-    let active_mods = load_mods_from_dir(&game_data.mods_path, true).await?;
+    // dot prefixed folders in the mods folder count as disabled
+    let (active_mods, hidden_mods): (Vec<_>, Vec<_>) = load_mods_from_dir(&game_data.mods_path, true)
+        .await?
+        .into_iter()
+        .partition(|imod| imod.active);
 
     let profile = get_active_profile().await?;
 
     let disabled_mods = game_data.profile_path.join(profile).join("disabled");
 
-    let inactive_mods = load_mods_from_dir(&disabled_mods, false).await?;
+    let inactive_mods = [load_mods_from_dir(&disabled_mods, false).await?, hidden_mods].concat();
 
     Ok((active_mods, inactive_mods))
 }
@@ -540,8 +1805,59 @@ where
     })
 }
 
+/// direct dependencies of `id`, and everything that would break without it
+fn mod_relations(graph: &DependencyGraph, id: &str) -> (Vec<ModRelation>, Vec<ModRelation>) {
+    let Some(node) = graph.find(id) else {
+        return Default::default();
+    };
+
+    let depends_on = graph
+        .dependencies(node)
+        .map(|edge| ModRelation {
+            id: edge.target_id.clone().into(),
+            name: edge
+                .to
+                .map(|to| graph.nodes[to].name.clone())
+                .unwrap_or_else(|| edge.target_id.clone())
+                .into(),
+            installed: edge.to.is_some(),
+            required: edge.required(),
+            indirect: false,
+        })
+        .collect();
+
+    let direct: HashSet<usize> = graph
+        .dependents(node)
+        .filter(|edge| edge.required())
+        .map(|edge| edge.from)
+        .collect();
+    let required_by = graph
+        .required_by_transitive(node)
+        .into_iter()
+        .map(|dependent| ModRelation {
+            id: graph.nodes[dependent].id.clone().into(),
+            name: graph.nodes[dependent].name.clone().into(),
+            installed: true,
+            required: true,
+            indirect: !direct.contains(&dependent),
+        })
+        .collect();
+
+    (depends_on, required_by)
+}
+
 async fn set_mod_active(modid: String, handle_copy: Weak<AppWindow>) -> Result<()> {
-    let imod = find_mod(&modid).await?;
+    let (active_mods, inactive_mods) = load_mods().await?;
+    let mods = [active_mods, inactive_mods].concat();
+
+    let imod = mods
+        .iter()
+        .find(|imod| imod.has_id(&modid))
+        .cloned()
+        .ok_or(io::Error::new(io::ErrorKind::NotFound, "Mod not found"))?;
+
+    let graph = DependencyGraph::new(&mods);
+    let (depends_on, required_by) = mod_relations(&graph, &modid);
 
     slint::invoke_from_event_loop(move || {
         let ui_weak = handle_copy.unwrap();
@@ -549,8 +1865,46 @@ async fn set_mod_active(modid: String, handle_copy: Weak<AppWindow>) -> Result<(
         if ui_weak.get_active_mod().id == modid && ui_weak.get_active_mod_active() {
             ui_weak.set_active_mod_active(false)
         } else {
-            ui_weak.set_active_mod(Mod::from(&imod));
+            let loaded = LOADED_MODS.lock().unwrap();
+            let metadata = loaded.facts.metadata(&modid).cloned().unwrap_or_default();
+            ui_weak.set_active_mod(mod_with_facts(&imod, &loaded.facts));
+            drop(loaded);
+            ui_weak.set_edit_tags(metadata.tags.join(", ").into());
+            ui_weak.set_edit_category(metadata.category.into());
+            ui_weak.set_edit_notes(metadata.notes.into());
             ui_weak.set_active_mod_active(true);
+            ui_weak.set_depends_on(ModelRc::new(VecModel::from(depends_on)));
+            ui_weak.set_required_by(ModelRc::new(VecModel::from(required_by)));
+            ui_weak.set_nexus_mod(NexusApiMod::default());
+            ui_weak.set_stored_versions(ModelRc::default());
+            spawn_logging(set_stored_versions(modid.clone(), handle_copy.clone()));
+            if let Some(nexus_id) = imod.manifest.nexus_id() {
+                spawn_logging(set_nexus_mod(modid, nexus_id, handle_copy));
+            }
+        }
+    })
+    .unwrap();
+
+    Ok(())
+}
+
+async fn set_nexus_mod(modid: String, nexus_id: u32, handle_copy: Weak<AppWindow>) -> Result<()> {
+    let game_data = get_game_data().await?;
+
+    let Some(client) = NexusClient::from_config(&game_data.config) else {
+        debug!("No nexus api key configured, skipping nexus lookup");
+        return Ok(());
+    };
+
+    let details = client.get_mod_details(nexus_id).await?;
+    debug!("Nexus mod {nexus_id}: {} files", details.files.len());
+
+    slint::invoke_from_event_loop(move || {
+        let ui_weak = handle_copy.unwrap();
+
+        // the user might have clicked another mod while we were waiting on nexus
+        if ui_weak.get_active_mod().id == modid {
+            ui_weak.set_nexus_mod(NexusApiMod::from(&details));
         }
     })
     .unwrap();
@@ -558,6 +1912,15 @@ async fn set_mod_active(modid: String, handle_copy: Weak<AppWindow>) -> Result<(
     Ok(())
 }
 
+async fn download(link: String, handle_copy: Weak<AppWindow>) -> Result<()> {
+    let game_data = get_game_data().await?;
+
+    let path = downloads::start(link, &game_data.config, &game_data.downloads_path).await?;
+    info!("Download finished: {path:?}");
+
+    reload(handle_copy).await
+}
+
 async fn clear_active_mod(handle_copy: Weak<AppWindow>) -> Result<()> {
     slint::invoke_from_event_loop(move || {
         let ui_weak = handle_copy.unwrap();
@@ -578,7 +1941,7 @@ async fn main() -> Result<()> {
     env_logger::init();
 
     // inits the game data - do it here so errors stay pretty :D
-    let _ = match get_game_data().await {
+    let game_data = match get_game_data().await {
         Ok(data) => data,
         Err(err) => {
             if err.to_string().contains("SVMM") {
@@ -586,16 +1949,22 @@ async fn main() -> Result<()> {
                 let setup_weak = setup.as_weak();
                 setup.on_save(move || {
                     let setup = setup_weak.unwrap();
-                    let strin = setup.get_path();
+                    let installation_path = PathBuf::from(setup.get_path().as_str());
                     let file = get_svmm_config();
-                    write(
-                        file,
-                        ron::to_string(&SVMMConfig {
-                            installation_path: strin.to_string().into(),
-                        })
-                        .unwrap(),
-                    )
-                    .unwrap();
+                    // keeps the other settings of a config that only lacked the game folder
+                    if let Err(err) = update_config_file(&file, &installation_path, |config| {
+                        config.installation_path = installation_path.clone()
+                    }) {
+                        warn!("Replacing the unreadable {file:?}: {err}");
+                        write_config_file(
+                            &file,
+                            &SVMMConfig {
+                                installation_path: installation_path.clone(),
+                                ..Default::default()
+                            },
+                        )
+                        .unwrap();
+                    }
                     slint::quit_event_loop().unwrap();
                 });
 
@@ -607,16 +1976,55 @@ async fn main() -> Result<()> {
         }
     };
 
+    if let Err(err) = i18n::init(
+        game_data.config.language.as_deref(),
+        &game_data.svmm_path.join("translations"),
+    ) {
+        warn!("Failed to set up translations: {err}");
+    }
+
+    if run_history_command().await? {
+        return Ok(());
+    }
+
     let ui = AppWindow::new()?;
 
+    let handle_weak = ui.as_weak();
+    downloads::set_listener(move |downloads| {
+        let handle_copy = handle_weak.clone();
+        slint::invoke_from_event_loop(move || {
+            let ui_weak = handle_copy.unwrap();
+            ui_weak.set_downloads(generic_to_modelrc::<downloads::Download, DownloadTask>(&downloads));
+        })
+        .unwrap();
+    });
+
+    // nxm:// links get handed to us as an argument when we're registered as the handler
+    for link in std::env::args().skip(1).filter(|arg| downloads::is_download_link(arg)) {
+        spawn_logging(download(link, ui.as_weak()));
+    }
+
+    // so do archives and folders, dropping them on the executable opens svmm with their paths
+    for path in std::env::args().skip(1).filter(|arg| !arg.starts_with("--")) {
+        if Path::new(&path).exists() {
+            spawn_logging(install_dropped(path, ui.as_weak()));
+        }
+    }
+
     // we love a quickly starting application
     spawn_logging(reload(ui.as_weak()));
+    spawn_logging(set_history_labels(ui.as_weak()));
+    spawn_logging(set_saves(ui.as_weak()));
 
     let handle_weak = ui.as_weak();
     ui.global::<Logic>().on_mod_move(move |value| {
         let v = value.to_string();
         let handle_copy = handle_weak.clone();
-        spawn_logging(switch_mod(v).and_then(|_| reload(handle_copy)));
+        spawn_logging(recorded(
+            describe_toggle(&v),
+            handle_copy.clone(),
+            toggle_mod(v, handle_copy),
+        ));
     });
 
     let handle_weak = ui.as_weak();
@@ -633,7 +2041,7 @@ async fn main() -> Result<()> {
     });
 
     ui.global::<Magic>().on_open(move |s| {
-        let result = opener::open(s.to_string());
+        let result = opener::open(s.as_str());
         if let Err(err) = result {
             error!("{}", err);
         }
@@ -657,14 +2065,32 @@ async fn main() -> Result<()> {
     ui.on_select_change(move |s| {
         let handle_copy = handle_weak.clone();
         let s = s.to_string();
-        spawn_logging(switch_to_profile(s).and_then(|_| reload(handle_copy)));
+        spawn_logging(recorded(
+            tr!("Switch to {}", s),
+            handle_copy.clone(),
+            change_profile(s.clone(), false, handle_copy),
+        ));
     });
 
     let handle_weak = ui.as_weak();
     ui.on_switch_mod(move || {
         let handle_copy = handle_weak.clone();
-        let modid = handle_copy.unwrap().get_active_mod().id.clone();
-        spawn_logging(switch_mod(modid.to_string()).and_then(|_| reload(handle_copy)));
+        let modid = handle_copy.unwrap().get_active_mod().id.to_string();
+        spawn_logging(recorded(
+            describe_toggle(&modid),
+            handle_copy.clone(),
+            toggle_mod(modid, handle_copy),
+        ));
+    });
+
+    let handle_weak = ui.as_weak();
+    ui.global::<Logic>().on_disable_mod(move |modid, with_dependents| {
+        let handle_copy = handle_weak.clone();
+        spawn_logging(recorded(
+            describe_toggle(&modid),
+            handle_copy.clone(),
+            disable_mod(modid.to_string(), with_dependents, handle_copy),
+        ));
     });
 
     let handle_weak = ui.as_weak();
@@ -676,87 +2102,1094 @@ async fn main() -> Result<()> {
     let handle_weak = ui.as_weak();
     ui.on_delete_mod(move || {
         let handle_copy = handle_weak.clone();
-        let handle_copy2 = handle_weak.clone();
-        let modid: String = handle_copy.unwrap().get_active_mod().id.clone().to_string();
-        spawn_logging(
-            remove_mod(modid.clone())
-                .and_then(|_| clear_active_mod(handle_copy))
-                .and_then(|_| reload(handle_copy2)),
-        );
+        let active_mod = handle_copy.unwrap().get_active_mod();
+        let modid: String = active_mod.id.to_string();
+        spawn_logging(recorded(
+            tr!("Delete {}", active_mod.text),
+            handle_weak.clone(),
+            delete_mod(modid, false, handle_copy),
+        ));
+    });
+
+    let handle_weak = ui.as_weak();
+    ui.global::<Logic>().on_delete_mod(move |modid| {
+        let handle_copy = handle_weak.clone();
+        let name = find_loaded_name(&modid);
+        spawn_logging(recorded(
+            tr!("Delete {}", name),
+            handle_weak.clone(),
+            delete_mod(modid.to_string(), false, handle_copy),
+        ));
+    });
+
+    let handle_weak = ui.as_weak();
+    ui.global::<Logic>().on_confirm_save_warning(move |action, target| {
+        let handle_copy = handle_weak.clone();
+        let target = target.to_string();
+        match action.as_str() {
+            "delete" => {
+                spawn_logging(recorded(
+                    tr!("Delete {}", target),
+                    handle_copy.clone(),
+                    delete_mod(target, true, handle_copy),
+                ));
+            }
+            "switch" => {
+                spawn_logging(recorded(
+                    tr!("Switch to {}", target),
+                    handle_copy.clone(),
+                    change_profile(target, true, handle_copy),
+                ));
+            }
+            "bulk-delete" => {
+                spawn_logging(recorded(
+                    tr!("Delete selected mods"),
+                    handle_copy.clone(),
+                    bulk_action(BulkAction::Delete, true, handle_copy),
+                ));
+            }
+            _ => error!("Unknown save warning action {action}"),
+        }
     });
 
     let handle_weak = ui.as_weak();
     ui.global::<Logic>().on_delete_zip(move |s| {
         let handle_copy = handle_weak.clone();
         spawn_logging(
-            tokio::fs::remove_file(s.to_string())
-                .map_err(|_| color_eyre::eyre::eyre!("Failed to delete file"))
+            tokio::fs::remove_file(s)
+                .map_err(|_| color_eyre::eyre::eyre!(tr!("Failed to delete file")))
                 .and_then(|_| reload(handle_copy)),
         );
     });
 
     let handle_weak = ui.as_weak();
-    ui.global::<Logic>().on_install_zip(move |s| {
-        let handle_copy = handle_weak.clone();
-        spawn_logging(unzip(s.to_string()).and_then(|_| reload(handle_copy)));
+    ui.global::<Logic>().on_select_zip_mod(move |zip, path, selected| {
+        select_zip_mod(&handle_weak.unwrap(), &zip, &path, selected);
     });
 
-    ui.run()?;
-    Ok(())
-}
-
-async fn unzip<A: AsRef<str>>(zip_path: A) -> Result<()> {
-    debug!("Installing mods from zip: {}", zip_path.as_ref());
-    let data = get_game_data().await?;
-    let p = PathBuf::from(zip_path.as_ref());
-    let zip = File::open(&p)?;
-    let mut archive = ZipArchive::new(zip)?;
+    let handle_weak = ui.as_weak();
+    ui.global::<Logic>().on_install_zip(move |s, disabled| {
+        let handle_copy = handle_weak.clone();
+        let selection = zip_selection(&handle_copy.unwrap(), &s);
+        let name = Path::new(s.as_str())
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        spawn_logging(recorded(tr!("Install {}", name), handle_copy.clone(), async move {
+            show_install_notices(
+                install_mods(s.to_string(), selection, disabled).await?,
+                handle_copy.clone(),
+            );
+            reload(handle_copy).await
+        }));
+    });
 
-    let mut path = data.mods_path.clone();
+    let handle_weak = ui.as_weak();
+    ui.global::<Logic>().on_install_local(move |id, kind, path| {
+        let handle_copy = handle_weak.clone();
+        spawn_logging(recorded(
+            tr!("Install {}", id),
+            handle_copy.clone(),
+            install_local_dependency(id.to_string(), kind.to_string(), path.to_string(), handle_copy),
+        ));
+    });
 
-    // unzip to zip directory if theres a manifest.json instead of the mod being in a subdirectory!
-    if archive.by_name("manifest.json").is_ok() {
-        let dir = p.file_name().unwrap().to_string_lossy().replace(".zip", "");
-        debug!("Mods not in subdirectory extracting to {dir}");
-        path = path.join(dir);
-    }
-    debug!("Extracting to {path:?}");
-    archive.extract(path)?;
-    debug!("Extracting complete!");
-    Ok(())
-}
+    let handle_weak = ui.as_weak();
+    ui.global::<Logic>().on_query_changed(move || {
+        refresh_mod_lists(&handle_weak.unwrap());
+    });
 
-async fn switch_to_profile(profile: String) -> Result<()> {
-    info!("Changing profile to: {profile:?}");
-    let game_data = get_game_data().await?;
-    let active_profile = get_active_profile().await?;
+    let handle_weak = ui.as_weak();
+    ui.global::<Logic>().on_check_updates(move || {
+        let handle_copy = handle_weak.clone();
+        spawn_logging(check_updates(handle_copy));
+    });
 
-    for file in read_dir(&game_data.mods_path)? {
-        let file = file?;
-        if file.path().is_file() {
-            continue;
+    let handle_weak = ui.as_weak();
+    ui.global::<Logic>().on_save_metadata(move |id, pinned, favorite| {
+        if let Err(err) = save_metadata(&handle_weak.unwrap(), &id, pinned, favorite) {
+            error!("Failed to save mod metadata: {err}");
         }
-        rename(
-            file.path(),
-            game_data
-                .profile_path
-                .join(&active_profile)
-                .join("enabled")
-                .join(file.path().file_name().unwrap()),
-        )?;
-    }
+    });
 
-    for file in read_dir(&game_data.profile_path.join(&profile).join("enabled"))? {
+    let handle_weak = ui.as_weak();
+    ui.global::<Logic>().on_toggle_group(move |group| {
+        toggle_group(&handle_weak.unwrap(), &group);
+    });
+
+    let handle_weak = ui.as_weak();
+    ui.global::<Logic>().on_switch_group(move |group, active| {
+        let handle_copy = handle_weak.clone();
+        let description = if active {
+            tr!("Disable group {}", group)
+        } else {
+            tr!("Enable group {}", group)
+        };
+        spawn_logging(recorded(
+            description,
+            handle_copy.clone(),
+            switch_group(group.to_string(), active, handle_copy),
+        ));
+    });
+
+    let handle_weak = ui.as_weak();
+    ui.global::<Logic>().on_select_mod(move |id, selected| {
+        select_mod(&handle_weak.unwrap(), &id, selected);
+    });
+
+    let handle_weak = ui.as_weak();
+    ui.global::<Logic>().on_select_all(move |selected| {
+        select_all(&handle_weak.unwrap(), selected);
+    });
+
+    let handle_weak = ui.as_weak();
+    ui.global::<Logic>().on_bulk_action(move |action, profile| {
+        let handle_copy = handle_weak.clone();
+        match BulkAction::parse(&action, profile.to_string()) {
+            Ok(action) => {
+                let description = match &action {
+                    BulkAction::Enable => tr!("Enable selected mods"),
+                    BulkAction::Disable => tr!("Disable selected mods"),
+                    BulkAction::Delete => tr!("Delete selected mods"),
+                    BulkAction::MoveToProfile(profile) => tr!("Move selected mods to {}", profile),
+                };
+                spawn_logging(recorded(
+                    description,
+                    handle_copy.clone(),
+                    bulk_action(action, false, handle_copy),
+                ));
+            }
+            Err(err) => error!("{err}"),
+        }
+    });
+
+    let handle_weak = ui.as_weak();
+    ui.global::<Logic>().on_set_tag_active(move |tag, active| {
+        let handle_copy = handle_weak.clone();
+        let description = if active {
+            tr!("Enable mods tagged {}", tag)
+        } else {
+            tr!("Disable mods tagged {}", tag)
+        };
+        spawn_logging(recorded(
+            description,
+            handle_copy.clone(),
+            set_tag_active(tag.to_string(), active, handle_copy),
+        ));
+    });
+
+    let handle_weak = ui.as_weak();
+    ui.global::<Logic>().on_undo(move || {
+        spawn_logging(undo(false, handle_weak.clone()));
+    });
+
+    let handle_weak = ui.as_weak();
+    ui.global::<Logic>().on_redo(move || {
+        spawn_logging(undo(true, handle_weak.clone()));
+    });
+
+    let handle_weak = ui.as_weak();
+    ui.global::<Logic>().on_refresh_saves(move || {
+        spawn_logging(set_saves(handle_weak.clone()));
+    });
+
+    let handle_weak = ui.as_weak();
+    ui.global::<Logic>().on_bind_save(move |folder, profile| {
+        let handle_copy = handle_weak.clone();
+        spawn_logging(async move {
+            bind_save(folder.to_string(), profile.to_string()).await?;
+            set_saves(handle_copy).await
+        });
+    });
+
+    let handle_weak = ui.as_weak();
+    ui.global::<Logic>().on_backup_save(move |folder| {
+        let handle_copy = handle_weak.clone();
+        spawn_logging(async move {
+            backup_save(folder.to_string()).await?;
+            set_saves(handle_copy).await
+        });
+    });
+
+    let handle_weak = ui.as_weak();
+    ui.global::<Logic>().on_launch_game(move || {
+        let handle_copy = handle_weak.clone();
+        spawn_logging(async move {
+            launch_game().await?;
+            set_saves(handle_copy).await
+        });
+    });
+
+    let handle_weak = ui.as_weak();
+    ui.global::<Logic>().on_rollback(move |id, path| {
+        let handle_copy = handle_weak.clone();
+        spawn_logging(recorded(tr!("Roll back {}", id), handle_copy.clone(), async move {
+            rollback(id.to_string(), path.to_string().into()).await?;
+            reload(handle_copy.clone()).await?;
+            set_stored_versions(id.to_string(), handle_copy).await
+        }));
+    });
+
+    ui.global::<Logic>().on_export_graph(move |format| {
+        spawn_logging(export_dependency_graph(format.to_string()));
+    });
+
+    let handle_weak = ui.as_weak();
+    ui.global::<Logic>().on_install_path(move |text| {
+        for path in dropped_paths(&text) {
+            spawn_logging(install_dropped(path, handle_weak.clone()));
+        }
+    });
+
+    // files dropped from a file manager never reach a DropArea, winit hands them out one event per file.
+    // winit doesn't support this on wayland, pasting the paths still works there
+    let handle_weak = ui.as_weak();
+    ui.window().on_winit_window_event(move |_, event| {
+        if let WindowEvent::DroppedFile(path) = event {
+            spawn_logging(install_dropped(path.to_string_lossy().to_string(), handle_weak.clone()));
+        }
+        EventResult::Propagate
+    });
+
+    let handle_weak = ui.as_weak();
+    ui.global::<Logic>().on_download(move |link| {
+        let handle_copy = handle_weak.clone();
+        spawn_logging(download(link.to_string(), handle_copy));
+    });
+
+    ui.global::<Logic>().on_cancel_download(move |id| {
+        downloads::cancel(id as u64);
+    });
+
+    let handle_weak = ui.as_weak();
+    ui.global::<Logic>().on_retry_download(move |id| {
+        let handle_copy = handle_weak.clone();
+        if let Some(link) = downloads::forget(id as u64) {
+            spawn_logging(download(link, handle_copy));
+        }
+    });
+
+    ui.global::<Logic>().on_clear_downloads(move || {
+        downloads::clear_finished();
+    });
+
+    let themes = set_themes(&ui, game_data.config.theme.as_deref());
+    let handle_weak = ui.as_weak();
+    ui.global::<Logic>().on_set_theme(move |index| {
+        let ui = handle_weak.unwrap();
+        // the first entry follows the system
+        let id = usize::try_from(index - 1)
+            .ok()
+            .and_then(|index| themes.get(index))
+            .map(|theme| theme.id.clone());
+        pick_theme(&ui, &themes, id.as_deref());
+        if let Err(err) = update_config_file(&get_svmm_config(), &game_data.installation_path, |config| {
+            config.theme = id
+        }) {
+            warn!("Failed to remember the theme: {err}");
+        }
+    });
+
+    ui.run()?;
+    Ok(())
+}
+
+/// extracts the mod roots of `zip_path` into their folders below `root`, skipping junk files
+fn extract_layout(zip_path: &Path, root: &Path, layout: &[InstallTarget]) -> Result<()> {
+    let mut archive = ZipArchive::new(File::open(zip_path)?)?;
+    for index in 0..archive.len() {
+        let mut file = archive.by_index(index)?;
+        let Some(name) = file.enclosed_name().map(Path::to_path_buf) else {
+            continue;
+        };
+        if is_junk(&name) {
+            continue;
+        }
+        // readmes and the like next to the mod folders aren't part of any mod
+        let Some((target, relative)) = layout
+            .iter()
+            .find_map(|target| Some((target, name.strip_prefix(&target.source).ok()?)))
+        else {
+            continue;
+        };
+        let destination = root.join(&target.folder).join(relative);
+        if file.is_dir() {
+            create_dir_all(&destination)?;
+            continue;
+        }
+        if let Some(parent) = destination.parent() {
+            create_dir_all(parent)?;
+        }
+        io::copy(&mut file, &mut File::create(&destination)?)?;
+    }
+    Ok(())
+}
+
+/// copies the mod roots of the unpacked folder `dir` into their folders below `root`, skipping junk files
+fn copy_layout(dir: &Path, root: &Path, layout: &[InstallTarget]) -> Result<()> {
+    for entry in WalkDir::new(dir) {
+        let entry = entry?;
+        let name = entry.path().strip_prefix(dir)?;
+        if is_junk(name) {
+            continue;
+        }
+        let Some((target, relative)) = layout
+            .iter()
+            .find_map(|target| Some((target, name.strip_prefix(&target.source).ok()?)))
+        else {
+            continue;
+        };
+        let destination = root.join(&target.folder).join(relative);
+        if entry.file_type().is_dir() {
+            create_dir_all(&destination)?;
+        } else {
+            if let Some(parent) = destination.parent() {
+                create_dir_all(parent)?;
+            }
+            std::fs::copy(entry.path(), &destination)?;
+        }
+    }
+    Ok(())
+}
+
+/// installs the mods of the zip or unpacked folder `source` in the mod folders `selection`, or every mod for `None`.
+/// Each mod root goes into its own folder no matter how deep the archive nests it. Disabled installs go into the
+/// disabled folder of the active profile. Returns a line for every copy somewhere else that got replaced
+async fn install_mods<A: AsRef<str>>(
+    source: A,
+    selection: Option<Vec<PathBuf>>,
+    disabled: bool,
+) -> Result<Vec<String>> {
+    debug!("Installing mods from: {}", source.as_ref());
+    let data = get_game_data().await?;
+    let p = PathBuf::from(source.as_ref());
+
+    let root = if disabled {
+        data.profile_path.join(get_active_profile().await?).join("disabled")
+    } else {
+        data.mods_path.clone()
+    };
+    create_dir_all(&root)?;
+
+    // copying a folder onto itself truncates every file in it
+    if p.is_dir() {
+        let source = p.canonicalize()?;
+        for installed in [&data.mods_path, &data.profile_path] {
+            let Ok(installed) = installed.canonicalize() else {
+                continue;
+            };
+            if source.starts_with(&installed) || installed.starts_with(&source) {
+                Err(color_eyre::eyre::eyre!(tr!(
+                    "{} overlaps with {}, only folders from elsewhere can be installed",
+                    p.display(),
+                    installed.display()
+                )))?;
+            }
+        }
+    }
+
+    let zip_mod = read_mod_source(&p)?;
+    let layout: Vec<InstallTarget> = zip_mod
+        .layout()
+        .into_iter()
+        .filter(|target| {
+            selection
+                .as_ref()
+                .is_none_or(|dirs| dirs.iter().any(|dir| dir.starts_with(&target.source)))
+        })
+        .collect();
+    for target in &layout {
+        debug!("{:?} goes to {}", target.source, target.folder);
+    }
+
+    // store the versions this archive replaces so they can be rolled back to, reinstalls just overwrite. A copy in
+    // another folder, like a group folder, a renamed folder or the other side of enabled and disabled, gets replaced
+    // too, smapi refuses to load a unique id twice. When it's the same version it goes to the deleted bin instead, the
+    // rollback list would only get a duplicate
+    let store = version_store(data);
+    let disabled_root = data.profile_path.join(get_active_profile().await?).join("disabled");
+    let (active_mods, inactive_mods) = load_mods().await?;
+    let mut replaced = vec![];
+    let mut archived = vec![];
+    let mut notices = vec![];
+    for imod in active_mods.iter().chain(&inactive_mods) {
+        let Some(target) = layout
+            .iter()
+            .find(|target| target.unique_id.eq_ignore_ascii_case(&imod.manifest.unique_id))
+        else {
+            continue;
+        };
+        let destination = root.join(&target.folder);
+        let upgraded = zip_mod.manifests().any(|manifest| {
+            manifest.unique_id.eq_ignore_ascii_case(&imod.manifest.unique_id)
+                && manifest.version != imod.manifest.version
+        });
+        if !upgraded && imod.path == destination {
+            continue;
+        }
+        if imod.path != destination {
+            let location = imod.path.strip_prefix(&data.installation_path).unwrap_or(&imod.path);
+            info!("Replacing {} at {:?}", imod.manifest.unique_id, imod.path);
+            notices.push(tr!("Replaced {} in {}", imod.manifest.name, location.display()));
+        }
+        let imod_root = if imod.path.starts_with(&data.mods_path) {
+            &data.mods_path
+        } else {
+            &disabled_root
+        };
+        let kept = if upgraded {
+            archived.push(imod.manifest.unique_id.clone());
+            store.archive(imod)?
+        } else {
+            move_to_deleted(&imod.path, &data.svmm_path.join("deleted"))?
+        };
+        replaced.push((kept, destination));
+        remove_empty_parents(&imod.path, imod_root)?;
+    }
+
+    // folders that didn't exist before can be moved to the deleted bin on undo, overwritten files can't be restored
+    let new_entries: Vec<PathBuf> = layout
+        .iter()
+        .map(|target| root.join(&target.folder))
+        .filter(|entry| !entry.exists())
+        .collect();
+
+    // reinstalls overwrite files, deployed library mods share theirs with the library and other profiles
+    if data.config.profile_storage == ProfileStorage::Library {
+        for entry in layout.iter().map(|target| root.join(&target.folder)) {
+            if entry.symlink_metadata().is_ok() {
+                library::detach(&entry)?;
+            }
+        }
+    }
+
+    debug!("Extracting to {root:?}");
+    if p.is_dir() {
+        copy_layout(&p, &root, &layout)?;
+    } else {
+        extract_layout(&p, &root, &layout)?;
+    }
+    debug!("Extracting complete!");
+
+    for (kept, target) in &replaced {
+        keep_config(kept, target)?;
+    }
+    let mut pruned = vec![];
+    for unique_id in &archived {
+        pruned.extend(store.prune(unique_id)?);
+    }
+    load_history(data).forget(&pruned)?;
+
+    let millis = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis();
+    for entry in new_entries {
+        let name = entry.file_name().unwrap().to_string_lossy().to_string();
+        history::record(Change::Move {
+            from: data.svmm_path.join("deleted").join(format!("{name}-{millis}")),
+            to: entry,
+        });
+    }
+
+    record_sources(&p, selection).await?;
+    Ok(notices)
+}
+
+/// shows the lines [`install_mods`] returned in the status line of the mod list
+fn show_install_notices(notices: Vec<String>, handle_copy: Weak<AppWindow>) {
+    if notices.is_empty() {
+        return;
+    }
+    slint::invoke_from_event_loop(move || {
+        handle_copy.unwrap().set_bulk_report(notices.join("\n").into());
+    })
+    .unwrap();
+}
+
+/// remembers which archive or folder the mods in `zip_path` were installed from
+async fn record_sources(zip_path: &Path, selection: Option<Vec<PathBuf>>) -> Result<()> {
+    let data = get_game_data().await?;
+    let zip_path = zip_path.to_path_buf();
+    let (zip, sha256) = history::spawn_blocking(move || -> Result<_> {
+        let hash = if zip_path.is_dir() {
+            library::hash_dir(&zip_path)?
+        } else {
+            downloads::sha256_file(&zip_path)?
+        };
+        Ok((read_mod_source(&zip_path)?, hash))
+    })
+    .await??;
+    let installed_at = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+
+    let mut loaded = LOADED_MODS.lock().unwrap();
+    // re-read the store so we don't lose edits made by another svmm window
+    let mut metadata = MetadataStore::load(data.svmm_path.join("metadata.ron"));
+    for manifest in zip.selected_manifests(selection.as_deref()) {
+        debug!(
+            "Recording {} {} from {:?}",
+            manifest.unique_id,
+            manifest.version,
+            zip.path()
+        );
+        metadata.record_source(
+            &manifest.unique_id,
+            ModSource {
+                archive: zip.path().to_path_buf(),
+                sha256: sha256.clone(),
+                installed_at,
+                version: manifest.version.clone(),
+            },
+        );
+    }
+    metadata.save()?;
+    loaded.facts.metadata = metadata;
+
+    Ok(())
+}
+
+async fn switch_to_profile(profile: String) -> Result<()> {
+    info!("Changing profile to: {profile:?}");
+    let game_data = get_game_data().await?;
+    let active_profile = get_active_profile().await?;
+
+    backup_saves(&[&active_profile, &profile]).await?;
+
+    if game_data.config.profile_storage == ProfileStorage::Library {
+        return switch_library_profile(&profile, &active_profile).await;
+    }
+
+    for file in read_dir(&game_data.mods_path)? {
+        let file = file?;
+        if file.path().is_file() {
+            continue;
+        }
+        rename_logged(
+            file.path(),
+            game_data
+                .profile_path
+                .join(&active_profile)
+                .join("enabled")
+                .join(file.path().file_name().unwrap()),
+        )?;
+    }
+
+    for file in read_dir(&game_data.profile_path.join(&profile).join("enabled"))? {
         let file = file?;
         if file.path().is_file() {
             continue;
         }
-        rename(file.path(), &game_data.mods_path.join(file.path().file_name().unwrap()))?;
+        rename_logged(file.path(), &game_data.mods_path.join(file.path().file_name().unwrap()))?;
     }
 
-    write(game_data.mods_path.join(".profile"), &profile)?;
+    write_logged(game_data.mods_path.join(".profile"), &profile)?;
 
     info!("Done loading profile reloading mods!");
 
     Ok(())
 }
+
+/// stashes the mods folder into the library and links in the mods of `profile`. Nothing gets recorded for undo and the
+/// history is cleared, the library keeps every mod around so switching back is just as cheap
+async fn switch_library_profile(profile: &str, active_profile: &str) -> Result<()> {
+    let game_data = get_game_data().await?;
+    let library = Library::new(game_data.svmm_path.join("library"), game_data.config.link_method);
+    let mods_path = game_data.mods_path.clone();
+    let from_profile_dir = game_data.profile_path.join(active_profile);
+    let profile_dir = game_data.profile_path.join(profile);
+    let profile = profile.to_owned();
+    let mut history = load_history(game_data);
+
+    // hashing every mod takes a while
+    history::spawn_blocking(move || -> Result<()> {
+        let entries = match ProfileManifest::load(&profile_dir)? {
+            Some(manifest) => manifest.mods,
+            // the first switch since turning on the library, the mods of the profile move into the library as they are
+            None if profile_dir.join("enabled").is_dir() => {
+                let mods = library.stash(&profile_dir.join("enabled"))?;
+                ProfileManifest { mods: mods.clone() }.save(&profile_dir)?;
+                mods
+            }
+            None => vec![],
+        };
+        library.switch(&mods_path, &from_profile_dir, &entries, &profile, &mut history)?;
+        info!("Deployed {profile} from the library");
+        Ok(())
+    })
+    .await?
+}
+
+impl From<&themes::Theme> for Theme {
+    fn from(theme: &themes::Theme) -> Self {
+        let palette = &theme.palette;
+        let fonts = &theme.fonts;
+        Theme {
+            name: theme.name.clone().into(),
+            palette: Palette {
+                menuBar: palette.menu_bar.0.into(),
+                mainContent: palette.main_content.0.into(),
+                r#box: palette.r#box.0.into(),
+                lightDisplay: palette.light_display.0.into(),
+                pieChart: palette.pie_chart.0.into(),
+                roundButton: palette.round_button.0.into(),
+                weekdayBox: palette.weekday_box.0.into(),
+                text: palette.text.0.into(),
+                shadow: palette.shadow.0.into(),
+                error: palette.error.0.into(),
+                warning: palette.warning.0.into(),
+                success: palette.success.0.into(),
+            },
+            default_font: fonts.default,
+            tiny_font: fonts.tiny,
+            small_font: fonts.small,
+            medium_font: fonts.medium,
+            large_font: fonts.large,
+            huge_font: fonts.huge,
+            title_font: fonts.title,
+        }
+    }
+}
+
+/// hands the built in and user themes to the ui and applies the remembered one, following the system switches
+/// between the built in dark and light themes
+fn set_themes(ui: &AppWindow, picked: Option<&str>) -> Vec<themes::Theme> {
+    let themes = themes::load_themes(&themes::user_themes_dir());
+    let skin = ui.global::<Skin>();
+    for theme in &themes {
+        match theme.id.as_str() {
+            "dark" => skin.set_dark_theme(theme.into()),
+            "light" => skin.set_light_theme(theme.into()),
+            _ => {}
+        }
+    }
+
+    let names = [
+        vec![tr!("System")],
+        themes.iter().map(|theme| theme.name.clone()).collect(),
+    ]
+    .concat();
+    ui.set_themes(generic_to_modelrc(&names));
+    pick_theme(ui, &themes, picked);
+    themes
+}
+
+/// switches to the theme with `id`, no id or an unknown one follows the system
+fn pick_theme(ui: &AppWindow, themes: &[themes::Theme], id: Option<&str>) {
+    let skin = ui.global::<Skin>();
+    let index = themes.iter().position(|theme| Some(theme.id.as_str()) == id);
+    match index {
+        Some(index) => {
+            info!("Using the {} theme", themes[index].id);
+            skin.set_picked_theme((&themes[index]).into());
+            skin.set_follow_system(false);
+            skin.invoke_style_widgets(false, themes[index].palette.is_dark());
+            ui.set_theme_index(index as i32 + 1);
+        }
+        None => {
+            skin.set_follow_system(true);
+            skin.invoke_style_widgets(true, false);
+            ui.set_theme_index(0);
+        }
+    }
+}
+
+fn version_store(game_data: &GameData) -> VersionStore {
+    VersionStore::new(
+        game_data.svmm_path.join("versions"),
+        game_data.config.versions_kept.unwrap_or(5),
+    )
+}
+
+impl From<&StoredVersion> for ModVersion {
+    fn from(version: &StoredVersion) -> Self {
+        let desc = format_description!("[year]-[month]-[day] [hour]:[minute]");
+        ModVersion {
+            version: version.version.clone().into(),
+            stored: OffsetDateTime::from_unix_timestamp((version.stored_at / 1000) as i64)
+                .ok()
+                .and_then(|date| date.format(desc).ok())
+                .unwrap_or_default()
+                .into(),
+            path: version.path.to_string_lossy().to_string().into(),
+        }
+    }
+}
+
+/// swaps the installed version of `id` for a stored one. The replaced version gets stored as well, so a rollback can
+/// be reverted by rolling forward again
+async fn rollback(id: String, path: PathBuf) -> Result<()> {
+    let game_data = get_game_data().await?;
+    let store = version_store(game_data);
+    let stored = store
+        .list(&id)?
+        .into_iter()
+        .find(|version| version.path == path)
+        .ok_or(color_eyre::eyre::eyre!(tr!(
+            "{} is not a stored version of {}",
+            path.display(),
+            id
+        )))?;
+
+    let (active_mods, inactive_mods) = load_mods().await?;
+    let installed = [active_mods, inactive_mods]
+        .concat()
+        .into_iter()
+        .find(|imod| imod.has_id(&id));
+
+    info!("Rolling {id} back to {}", stored.version);
+    match installed {
+        // disabled mods stay disabled
+        Some(imod) => {
+            let replaced = store.archive(&imod)?;
+            rename_logged(&stored.path, &imod.path)?;
+            keep_config(&replaced, &imod.path)?;
+        }
+        None => rename_logged(&stored.path, game_data.mods_path.join(stored.path.file_name().unwrap()))?,
+    }
+    load_history(game_data).forget(&store.prune(&id)?)
+}
+
+async fn set_stored_versions(id: String, handle_copy: Weak<AppWindow>) -> Result<()> {
+    let versions = version_store(get_game_data().await?).list(&id)?;
+
+    slint::invoke_from_event_loop(move || {
+        let ui_weak = handle_copy.unwrap();
+
+        if ui_weak.get_active_mod().id == id {
+            ui_weak.set_stored_versions(generic_to_modelrc(&versions));
+        }
+    })
+    .unwrap();
+
+    Ok(())
+}
+
+fn saves_path(game_data: &GameData) -> Option<PathBuf> {
+    game_data.config.saves_path.clone().or_else(default_saves_path)
+}
+
+fn save_bindings(game_data: &GameData) -> SaveBindings {
+    SaveBindings::load(game_data.svmm_path.join("saves.ron"))
+}
+
+fn save_backups(game_data: &GameData) -> SaveBackups {
+    SaveBackups::new(
+        game_data.svmm_path.join("save-backups"),
+        game_data.config.save_backups_kept.unwrap_or(10),
+    )
+}
+
+/// backs up every save that can be played with one of `profiles`, before their mods change under them
+async fn backup_saves(profiles: &[&str]) -> Result<()> {
+    let game_data = get_game_data().await?;
+    let Some(saves_path) = saves_path(game_data) else {
+        return Ok(());
+    };
+    let backups = save_backups(game_data);
+    let bindings = save_bindings(game_data);
+    let profiles: Vec<String> = profiles.iter().map(|profile| profile.to_string()).collect();
+
+    // saves can be tens of megabytes
+    tokio::task::spawn_blocking(move || -> Result<()> {
+        let saves = find_saves(&saves_path)?;
+        let profiles: Vec<&str> = profiles.iter().map(String::as_str).collect();
+        for save in bindings.affected(&saves, &profiles) {
+            backups.backup(save)?;
+        }
+        Ok(())
+    })
+    .await?
+}
+
+async fn backup_save(folder: String) -> Result<()> {
+    let game_data = get_game_data().await?;
+    let saves_path = saves_path(game_data).ok_or(color_eyre::eyre::eyre!(tr!("No saves folder")))?;
+    let save = find_saves(&saves_path)?
+        .into_iter()
+        .find(|save| save.folder == folder)
+        .ok_or(color_eyre::eyre::eyre!(tr!("Save {} not found", folder)))?;
+    let backups = save_backups(game_data);
+    tokio::task::spawn_blocking(move || backups.backup(&save)).await??;
+    Ok(())
+}
+
+/// an empty `profile` unbinds the save
+async fn bind_save(folder: String, profile: String) -> Result<()> {
+    let mut bindings = save_bindings(get_game_data().await?);
+    info!("Binding save {folder} to {profile:?}");
+    bindings.set(&folder, Some(profile).filter(|profile| !profile.is_empty()));
+    bindings.save()
+}
+
+/// starts the game through smapi once the saves of the active profile are backed up
+async fn launch_game() -> Result<()> {
+    let game_data = get_game_data().await?;
+    backup_saves(&[&get_active_profile().await?]).await?;
+
+    let smapi = game_data.installation_path.join(if cfg!(windows) {
+        "StardewModdingAPI.exe"
+    } else {
+        "StardewModdingAPI"
+    });
+    info!("Launching {smapi:?}");
+    i18n::system_env(&mut std::process::Command::new(&smapi))
+        .current_dir(&game_data.installation_path)
+        .spawn()?;
+    Ok(())
+}
+
+async fn set_saves(handle_copy: Weak<AppWindow>) -> Result<()> {
+    let game_data = get_game_data().await?;
+    let saves_path = saves_path(game_data);
+    let saves = match &saves_path {
+        Some(saves_path) => find_saves(saves_path)?,
+        None => vec![],
+    };
+    let bindings = save_bindings(game_data);
+    let backups = save_backups(game_data);
+
+    let mut slots = vec![];
+    for save in &saves {
+        let existing = backups.list(&save.folder)?;
+        slots.push(SaveSlot {
+            folder: save.folder.clone().into(),
+            farmer: save.farmer.clone().into(),
+            farm: save.farm.clone().into(),
+            date: save.date().into(),
+            played: save.play_time().into(),
+            profile: bindings.get(&save.folder).cloned().unwrap_or_default().into(),
+            backups: existing.len() as i32,
+            last_backup: existing.first().cloned().unwrap_or_default().into(),
+            backups_path: game_data
+                .svmm_path
+                .join("save-backups")
+                .join(&save.folder)
+                .to_string_lossy()
+                .to_string()
+                .into(),
+        });
+    }
+    let saves_path = saves_path
+        .map(|path| path.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    slint::invoke_from_event_loop(move || {
+        let ui_weak = handle_copy.unwrap();
+
+        ui_weak.set_saves_path(saves_path.into());
+        ui_weak.set_saves(ModelRc::new(VecModel::from(slots)));
+    })
+    .unwrap();
+
+    Ok(())
+}
+
+/// lowercase unique ids of the mods `profile` puts into the mods folder
+async fn profile_mod_ids(profile: &str) -> Result<HashSet<String>> {
+    let game_data = get_game_data().await?;
+    let mods = if profile == get_active_profile().await? {
+        load_mods().await?.0
+    } else {
+        let profile_dir = game_data.profile_path.join(profile);
+        match (game_data.config.profile_storage, ProfileManifest::load(&profile_dir)?) {
+            (ProfileStorage::Library, Some(manifest)) => {
+                let mut mods = vec![];
+                // dot prefixed entries are disabled mods, same as in the mods folder
+                for entry in manifest
+                    .mods
+                    .into_iter()
+                    .filter(|entry| !is_hidden(Path::new(&entry.folder)))
+                {
+                    let path = game_data.svmm_path.join("library").join(entry.hash);
+                    mods.extend(load_mods_from_dir(&path, true).await?);
+                }
+                mods
+            }
+            _ => load_mods_from_dir(&profile_dir.join("enabled"), true).await?,
+        }
+    };
+    Ok(mods
+        .iter()
+        .filter(|imod| imod.active)
+        .map(|imod| imod.manifest.unique_id.to_ascii_lowercase())
+        .collect())
+}
+
+/// which of the `dropped` mods, as `(unique id, name)`, the saves played with `profile` still reference. One line
+/// per save and mod
+async fn save_references(profile: &str, dropped: Vec<(String, String)>) -> Result<Vec<String>> {
+    let game_data = get_game_data().await?;
+    let Some(saves_path) = saves_path(game_data) else {
+        return Ok(vec![]);
+    };
+    if dropped.is_empty() {
+        return Ok(vec![]);
+    }
+    let saves = find_saves(&saves_path)?;
+    let affected: Vec<SaveGame> = save_bindings(game_data)
+        .affected(&saves, &[profile])
+        .into_iter()
+        .cloned()
+        .collect();
+
+    tokio::task::spawn_blocking(move || -> Result<Vec<String>> {
+        let mut lines = vec![];
+        for save in affected {
+            let scan = scan_save(&save.path.join(&save.folder))?;
+            for (id, name) in &dropped {
+                let count = scan.references(id);
+                if count > 0 {
+                    lines.push(tr!(
+                        "{} Farm ({}) uses {} things from {}",
+                        save.farm,
+                        save.farmer,
+                        count,
+                        name
+                    ));
+                }
+            }
+        }
+        Ok(lines)
+    })
+    .await?
+}
+
+fn show_save_warning(
+    action: &str,
+    target: String,
+    title: String,
+    references: Vec<String>,
+    handle_copy: Weak<AppWindow>,
+) {
+    info!("Asking before {action} {target}, {} save references", references.len());
+    let action = action.to_owned();
+    slint::invoke_from_event_loop(move || {
+        let ui_weak = handle_copy.unwrap();
+        ui_weak.set_save_warning(SaveWarning {
+            action: action.into(),
+            target: target.into(),
+            title: title.into(),
+            references: generic_to_modelrc(&references),
+        });
+        ui_weak.set_save_warning_open(true);
+    })
+    .unwrap();
+}
+
+/// deletes a mod, asks first when a save of the active profile still uses it
+async fn delete_mod(id: String, confirmed: bool, handle_copy: Weak<AppWindow>) -> Result<()> {
+    if !confirmed {
+        let imod = find_mod(&id).await?;
+        let dropped = vec![(id.clone(), imod.manifest.name.clone())];
+        let references = save_references(&get_active_profile().await?, dropped).await?;
+        if !references.is_empty() {
+            let title = tr!("These saves still use {}:", imod.manifest.name);
+            show_save_warning("delete", id, title, references, handle_copy);
+            return Ok(());
+        }
+    }
+
+    remove_mod(&id).await?;
+    clear_active_mod(handle_copy.clone()).await?;
+    reload(handle_copy).await
+}
+
+/// switches profiles, asks first when the saves played with `profile` use mods it doesn't have
+async fn change_profile(profile: String, confirmed: bool, handle_copy: Weak<AppWindow>) -> Result<()> {
+    if !confirmed {
+        let kept = profile_mod_ids(&profile).await?;
+        let dropped = load_mods()
+            .await?
+            .0
+            .into_iter()
+            .filter(|imod| !kept.contains(&imod.manifest.unique_id.to_ascii_lowercase()))
+            .map(|imod| (imod.manifest.unique_id, imod.manifest.name))
+            .collect();
+        let references = save_references(&profile, dropped).await?;
+        if !references.is_empty() {
+            let title = tr!("{} is missing mods these saves use:", profile);
+            show_save_warning("switch", profile, title, references, handle_copy);
+            return Ok(());
+        }
+    }
+
+    switch_to_profile(profile).await?;
+    reload(handle_copy).await
+}
+
+/// paths pasted or dropped as text, one per line. File managers hand out `file://` uris when copying files
+fn dropped_paths(text: &str) -> Vec<String> {
+    text.lines()
+        .map(|line| line.trim().trim_matches(['"', '\'']))
+        .filter(|line| !line.is_empty())
+        .map(|line| match line.strip_prefix("file://") {
+            Some(uri) => {
+                let path = percent_decode_str(uri).decode_utf8_lossy().to_string();
+                // file:///C:/Mods.zip on windows
+                match path.strip_prefix('/') {
+                    Some(windows_path) if cfg!(windows) && windows_path.get(1..2) == Some(":") => {
+                        windows_path.to_owned()
+                    }
+                    _ => path,
+                }
+            }
+            None => line.to_owned(),
+        })
+        .collect()
+}
+
+/// installs a zip or folder from anywhere on disk, like the ones in the downloads folder
+async fn install_dropped(path: String, handle_copy: Weak<AppWindow>) -> Result<()> {
+    let name = Path::new(&path)
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    info!("Installing {path}");
+    recorded(tr!("Install {}", name), handle_copy.clone(), async move {
+        show_install_notices(install_mods(path, None, false).await?, handle_copy.clone());
+        reload(handle_copy).await
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn installed(id: &str, dependencies: &[&str]) -> InstalledMod {
+        let dependencies: Vec<_> = dependencies.iter().map(|id| json!({"UniqueID": id})).collect();
+        let manifest = json!({
+            "Name": id,
+            "Author": "someone",
+            "Version": "1.0.0",
+            "UniqueID": id,
+            "Dependencies": dependencies,
+        });
+        InstalledMod {
+            path: PathBuf::from(id),
+            active: true,
+            modified: SystemTime::UNIX_EPOCH,
+            manifest: serde_json::from_value(manifest).unwrap(),
+            group: None,
+            diagnostics: vec![],
+        }
+    }
+
+    #[test]
+    fn dependencies_match_ids_case_insensitively() {
+        let mods = [
+            installed("Pathoschild.ContentPatcher", &[]),
+            installed(
+                "Someone.Pack",
+                &["pathoschild.contentpatcher", "spacechase0.JsonAssets"],
+            ),
+            installed("Someone.Other", &["SpaceChase0.jsonassets "]),
+        ];
+        let missing = find_missing_dependencies(&mods);
+        assert_eq!(missing.len(), 1);
+        assert_eq!(missing[0].modid, "spacechase0.JsonAssets");
+        assert_eq!(missing[0].for_mods, ["Someone.Pack", "Someone.Other"]);
+        assert!(missing[0].required);
+    }
+}
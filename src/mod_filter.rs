@@ -0,0 +1,393 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    dependency_graph::DependencyGraph,
+    i18n::tr,
+    mod_metadata::{MetadataStore, ModMetadata},
+    InstalledMod,
+};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ModFilter {
+    #[default]
+    All,
+    Enabled,
+    Disabled,
+    HasUpdate,
+    HasErrors,
+    Framework,
+}
+
+impl From<i32> for ModFilter {
+    /// indexes of the filter combobox in `modlist.slint`
+    fn from(index: i32) -> Self {
+        match index {
+            1 => ModFilter::Enabled,
+            2 => ModFilter::Disabled,
+            3 => ModFilter::HasUpdate,
+            4 => ModFilter::HasErrors,
+            5 => ModFilter::Framework,
+            _ => ModFilter::All,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SortKey {
+    #[default]
+    Modified,
+    Name,
+    Author,
+    Id,
+}
+
+impl From<i32> for SortKey {
+    /// indexes of the sort combobox in `modlist.slint`
+    fn from(index: i32) -> Self {
+        match index {
+            1 => SortKey::Name,
+            2 => SortKey::Author,
+            3 => SortKey::Id,
+            _ => SortKey::Modified,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ModQuery {
+    pub search: String,
+    pub filter: ModFilter,
+    pub sort: SortKey,
+    /// only mods with this tag, empty for every mod
+    pub tag: String,
+}
+
+/// everything the filters need to know that isn't in the manifest, keyed by lowercase unique id
+#[derive(Clone, Debug, Default)]
+pub struct ModFacts {
+    pub updates: HashMap<String, String>,
+    pub errors: HashMap<String, Vec<String>>,
+    pub frameworks: HashSet<String>,
+    pub metadata: MetadataStore,
+}
+
+fn key(id: &str) -> String {
+    id.trim().to_ascii_lowercase()
+}
+
+impl ModFacts {
+    /// frameworks and missing dependencies come from the dependency graph, updates are kept from the last check
+    pub fn new(mods: &[InstalledMod], updates: HashMap<String, String>, metadata: MetadataStore) -> Self {
+        let graph = DependencyGraph::new(mods);
+        let mut facts = ModFacts {
+            updates,
+            metadata,
+            ..Default::default()
+        };
+
+        for imod in mods {
+            let errors: Vec<String> = imod.errors().map(str::to_owned).collect();
+            if !errors.is_empty() {
+                facts
+                    .errors
+                    .entry(key(&imod.manifest.unique_id))
+                    .or_default()
+                    .extend(errors);
+            }
+        }
+
+        for edge in graph.edges.iter().filter(|edge| edge.required()) {
+            let from = &graph.nodes[edge.from];
+            match edge.to {
+                Some(to) => {
+                    facts.frameworks.insert(key(&graph.nodes[to].id));
+                    if from.active && !graph.nodes[to].active {
+                        facts
+                            .errors
+                            .entry(key(&from.id))
+                            .or_default()
+                            .push(tr!("{} is disabled", graph.nodes[to].name));
+                    }
+                }
+                None => facts
+                    .errors
+                    .entry(key(&from.id))
+                    .or_default()
+                    .push(tr!("{} is not installed", edge.target_id)),
+            }
+        }
+
+        facts
+    }
+
+    pub fn update(&self, id: &str) -> Option<&String> {
+        self.updates.get(&key(id))
+    }
+
+    pub fn errors(&self, id: &str) -> &[String] {
+        self.errors.get(&key(id)).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    pub fn is_framework(&self, id: &str) -> bool {
+        self.frameworks.contains(&key(id))
+    }
+
+    pub fn metadata(&self, id: &str) -> Option<&ModMetadata> {
+        self.metadata.get(id)
+    }
+}
+
+/// scores how well `query` matches `text`, `None` if not every character of the query shows up in order. Consecutive
+/// characters and matches at the start of words score higher, plain substring matches beat everything else
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i64> {
+    let query: Vec<char> = query.to_lowercase().chars().filter(|c| !c.is_whitespace()).collect();
+    if query.is_empty() {
+        return Some(0);
+    }
+    let text_lower = text.to_lowercase();
+    let chars: Vec<char> = text_lower.chars().collect();
+
+    let mut score = 0;
+    let mut query_index = 0;
+    let mut previous_match: Option<usize> = None;
+
+    for (i, c) in chars.iter().enumerate() {
+        if query_index == query.len() {
+            break;
+        }
+        if *c != query[query_index] {
+            continue;
+        }
+        score += 1;
+        if previous_match.is_some_and(|previous| previous + 1 == i) {
+            score += 5;
+        }
+        if i == 0 || !chars[i - 1].is_alphanumeric() {
+            score += 3;
+        }
+        previous_match = Some(i);
+        query_index += 1;
+    }
+
+    if query_index < query.len() {
+        return None;
+    }
+
+    if text_lower.contains(&query.iter().collect::<String>()) {
+        score += 100;
+    }
+
+    // prefer shorter texts when everything else is equal
+    Some(score * 1000 - chars.len() as i64)
+}
+
+fn search_score(imod: &InstalledMod, search: &str, metadata: Option<&ModMetadata>) -> Option<i64> {
+    let manifest = &imod.manifest;
+    let metadata_scores = metadata.into_iter().flat_map(|metadata| {
+        metadata
+            .tags
+            .iter()
+            .chain([&metadata.category, &metadata.notes])
+            .map(|text| fuzzy_score(search, text))
+    });
+    [
+        fuzzy_score(search, &manifest.name).map(|score| score * 4),
+        fuzzy_score(search, &manifest.unique_id).map(|score| score * 2),
+        fuzzy_score(search, &manifest.author).map(|score| score * 2),
+        manifest
+            .description
+            .as_deref()
+            .and_then(|description| fuzzy_score(search, description)),
+    ]
+    .into_iter()
+    .chain(metadata_scores)
+    .flatten()
+    .max()
+}
+
+fn matches_filter(imod: &InstalledMod, filter: ModFilter, facts: &ModFacts) -> bool {
+    let id = &imod.manifest.unique_id;
+    match filter {
+        ModFilter::All => true,
+        ModFilter::Enabled => imod.active,
+        ModFilter::Disabled => !imod.active,
+        ModFilter::HasUpdate => facts.update(id).is_some(),
+        ModFilter::HasErrors => !facts.errors(id).is_empty(),
+        ModFilter::Framework => facts.is_framework(id),
+    }
+}
+
+/// filters and sorts `mods`, a search sorts by relevance first and uses the sort key to break ties. Searching also
+/// looks at tags, categories and notes
+pub fn apply<'a>(mods: &'a [InstalledMod], query: &ModQuery, facts: &ModFacts) -> Vec<&'a InstalledMod> {
+    let search = query.search.trim();
+
+    let mut result: Vec<(i64, &InstalledMod)> = mods
+        .iter()
+        .filter(|imod| matches_filter(imod, query.filter, facts))
+        .filter(|imod| {
+            query.tag.is_empty()
+                || facts
+                    .metadata(&imod.manifest.unique_id)
+                    .is_some_and(|metadata| metadata.has_tag(&query.tag))
+        })
+        .filter_map(|imod| {
+            if search.is_empty() {
+                Some((0, imod))
+            } else {
+                search_score(imod, search, facts.metadata(&imod.manifest.unique_id)).map(|score| (score, imod))
+            }
+        })
+        .collect();
+
+    // stable sorts, mods come in sorted by modification time
+    match query.sort {
+        SortKey::Modified => {}
+        SortKey::Name => result.sort_by_key(|(_, imod)| imod.manifest.name.to_lowercase()),
+        SortKey::Author => result.sort_by_key(|(_, imod)| imod.manifest.author.to_lowercase()),
+        SortKey::Id => result.sort_by_key(|(_, imod)| imod.manifest.unique_id.to_lowercase()),
+    }
+    result.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+    // pinned mods stay on top no matter what
+    result.sort_by_key(|(_, imod)| {
+        !facts
+            .metadata(&imod.manifest.unique_id)
+            .is_some_and(|metadata| metadata.pinned)
+    });
+
+    result.into_iter().map(|(_, imod)| imod).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::PathBuf, time::SystemTime};
+
+    use super::*;
+    use crate::{ModDependency, ModManifest};
+
+    fn installed(id: &str, name: &str, author: &str, active: bool) -> InstalledMod {
+        InstalledMod {
+            path: PathBuf::from(id),
+            active,
+            modified: SystemTime::UNIX_EPOCH,
+            manifest: ModManifest {
+                name: name.to_owned(),
+                author: author.to_owned(),
+                version: "1.0.0".to_owned(),
+                unique_id: id.to_owned(),
+                ..Default::default()
+            },
+            group: None,
+            diagnostics: vec![],
+        }
+    }
+
+    fn ids(mods: &[&InstalledMod]) -> Vec<String> {
+        mods.iter().map(|imod| imod.manifest.unique_id.clone()).collect()
+    }
+
+    #[test]
+    fn fuzzy_score_prefers_substrings_word_starts_and_short_texts() {
+        assert_eq!(fuzzy_score("", "anything"), Some(0));
+        assert_eq!(fuzzy_score("  ", "anything"), Some(0));
+        assert_eq!(fuzzy_score("xyz", "Content Patcher"), None);
+        // every character has to show up in order
+        assert_eq!(fuzzy_score("rc", "Content Patcher"), None);
+
+        let substring = fuzzy_score("patch", "Content Patcher").unwrap();
+        let scattered = fuzzy_score("cpr", "Content Patcher").unwrap();
+        assert!(substring > scattered);
+        // case and whitespace in the query don't matter
+        assert_eq!(fuzzy_score("Con Tent", "content"), fuzzy_score("content", "content"));
+        assert!(fuzzy_score("cp", "Content Patcher").unwrap() > fuzzy_score("cp", "accept").unwrap());
+        assert!(fuzzy_score("lib", "Lib").unwrap() > fuzzy_score("lib", "Library").unwrap());
+    }
+
+    #[test]
+    fn apply_filters_searches_and_sorts() {
+        let mut framework = installed("Pathoschild.ContentPatcher", "Content Patcher", "Pathoschild", true);
+        framework.manifest.description = Some("Loads content packs".to_owned());
+        let mut pack = installed("Someone.Pack", "Seasonal Outfits", "Someone", true);
+        pack.manifest.dependencies = vec![ModDependency {
+            unique_id: "pathoschild.contentpatcher".to_owned(),
+            version: None,
+            required: true,
+            other: Default::default(),
+        }];
+        let mut broken = installed("Broken.Mod", "Broken", "Anyone", false);
+        broken.manifest.dependencies = vec![ModDependency {
+            unique_id: "Missing.Framework".to_owned(),
+            version: None,
+            required: true,
+            other: Default::default(),
+        }];
+        let mods = [pack, framework, broken];
+
+        let mut metadata = MetadataStore::default();
+        metadata.set(
+            "someone.pack",
+            ModMetadata {
+                tags: vec!["Outfits".to_owned()],
+                ..Default::default()
+            },
+        );
+        metadata.set(
+            "broken.mod",
+            ModMetadata {
+                pinned: true,
+                notes: "patch this later".to_owned(),
+                ..Default::default()
+            },
+        );
+        let updates = HashMap::from([("pathoschild.contentpatcher".to_owned(), "2.0.0".to_owned())]);
+        let facts = ModFacts::new(&mods, updates, metadata);
+
+        let query = |filter, search: &str, sort, tag: &str| ModQuery {
+            search: search.to_owned(),
+            filter,
+            sort,
+            tag: tag.to_owned(),
+        };
+        let all = |sort| query(ModFilter::All, "", sort, "");
+
+        // pinned mods come first, the rest keep their sort order
+        assert_eq!(
+            ids(&apply(&mods, &all(SortKey::Modified), &facts)),
+            ["Broken.Mod", "Someone.Pack", "Pathoschild.ContentPatcher"]
+        );
+        assert_eq!(
+            ids(&apply(&mods, &all(SortKey::Name), &facts)),
+            ["Broken.Mod", "Pathoschild.ContentPatcher", "Someone.Pack"]
+        );
+        assert_eq!(
+            ids(&apply(&mods, &all(SortKey::Author), &facts)),
+            ["Broken.Mod", "Pathoschild.ContentPatcher", "Someone.Pack"]
+        );
+
+        let filtered = |filter| ids(&apply(&mods, &query(filter, "", SortKey::Id, ""), &facts));
+        assert_eq!(
+            filtered(ModFilter::Enabled),
+            ["Pathoschild.ContentPatcher", "Someone.Pack"]
+        );
+        assert_eq!(filtered(ModFilter::Disabled), ["Broken.Mod"]);
+        assert_eq!(filtered(ModFilter::HasUpdate), ["Pathoschild.ContentPatcher"]);
+        assert_eq!(filtered(ModFilter::HasErrors), ["Broken.Mod"]);
+        assert_eq!(filtered(ModFilter::Framework), ["Pathoschild.ContentPatcher"]);
+
+        // tags are matched case insensitively
+        let tagged = apply(&mods, &query(ModFilter::All, "", SortKey::Modified, "outfits"), &facts);
+        assert_eq!(ids(&tagged), ["Someone.Pack"]);
+
+        // the name beats the description, notes are searched too
+        let searched = apply(&mods, &query(ModFilter::All, "content", SortKey::Modified, ""), &facts);
+        assert_eq!(ids(&searched), ["Pathoschild.ContentPatcher"]);
+        let searched = apply(&mods, &query(ModFilter::All, "patch", SortKey::Modified, ""), &facts);
+        assert_eq!(ids(&searched), ["Broken.Mod", "Pathoschild.ContentPatcher"]);
+        let searched = apply(
+            &mods,
+            &query(ModFilter::Enabled, "patch", SortKey::Modified, ""),
+            &facts,
+        );
+        assert_eq!(ids(&searched), ["Pathoschild.ContentPatcher"]);
+    }
+}
@@ -0,0 +1,235 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs::{create_dir_all, read_to_string, rename, write},
+    path::PathBuf,
+    time::UNIX_EPOCH,
+};
+
+use color_eyre::Result;
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::{diagnostics::is_placeholder, InstalledMod};
+
+/// everything the user knows about a mod that isn't in its manifest
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ModMetadata {
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub notes: String,
+    #[serde(default)]
+    pub category: String,
+    #[serde(default)]
+    pub pinned: bool,
+    #[serde(default)]
+    pub favorite: bool,
+    /// unix seconds of when svmm first saw the mod
+    #[serde(default)]
+    pub installed_at: Option<u64>,
+    /// the archive the mod was last installed from, `None` for mods installed outside of svmm
+    #[serde(default)]
+    pub source: Option<ModSource>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ModSource {
+    pub archive: PathBuf,
+    pub sha256: String,
+    /// unix seconds
+    pub installed_at: u64,
+    /// the manifest version at install time
+    pub version: String,
+}
+
+impl ModMetadata {
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|other| other.eq_ignore_ascii_case(tag.trim()))
+    }
+}
+
+/// splits user input like `qol, visuals ,, npc` into trimmed tags without duplicates
+pub fn parse_tags(input: &str) -> Vec<String> {
+    let mut tags: Vec<String> = vec![];
+    for tag in input.split(',').map(str::trim).filter(|tag| !tag.is_empty()) {
+        if !tags.iter().any(|other| other.eq_ignore_ascii_case(tag)) {
+            tags.push(tag.to_owned());
+        }
+    }
+    tags
+}
+
+/// metadata keyed by lowercase unique id, stored as ron in the svmm folder so it survives moving mods between the
+/// enabled and disabled folders and switching profiles
+#[derive(Clone, Debug, Default)]
+pub struct MetadataStore {
+    path: PathBuf,
+    mods: HashMap<String, ModMetadata>,
+}
+
+fn key(id: &str) -> String {
+    id.trim().to_ascii_lowercase()
+}
+
+impl MetadataStore {
+    pub fn load(path: PathBuf) -> Self {
+        let mods = match read_to_string(&path) {
+            Ok(content) => ron::from_str(&content).unwrap_or_else(|err| {
+                // keep the broken file around instead of overwriting the users notes on the next save
+                let backup = path.with_extension("ron.corrupt");
+                warn!("Failed to parse mod metadata {path:?}, moving it to {backup:?}: {err}");
+                if let Err(err) = rename(&path, &backup) {
+                    warn!("Failed to move corrupt metadata: {err}");
+                }
+                Default::default()
+            }),
+            Err(_) => Default::default(),
+        };
+        Self { path, mods }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            create_dir_all(parent)?;
+        }
+        write(&self.path, ron::ser::to_string_pretty(&self.mods, Default::default())?)?;
+        Ok(())
+    }
+
+    pub fn get(&self, id: &str) -> Option<&ModMetadata> {
+        self.mods.get(&key(id))
+    }
+
+    pub fn set(&mut self, id: &str, metadata: ModMetadata) {
+        self.mods.insert(key(id), metadata);
+    }
+
+    /// every tag in use, sorted case insensitively
+    pub fn tags(&self) -> Vec<String> {
+        let tags: BTreeSet<(String, &String)> = self
+            .mods
+            .values()
+            .flat_map(|metadata| &metadata.tags)
+            .map(|tag| (tag.to_lowercase(), tag))
+            .collect();
+        let mut result: Vec<String> = vec![];
+        for (lower, tag) in tags {
+            if result.last().map(|last| last.to_lowercase()) != Some(lower) {
+                result.push(tag.clone());
+            }
+        }
+        result
+    }
+
+    /// remembers where `id` came from, reinstalling resets the install date
+    pub fn record_source(&mut self, id: &str, source: ModSource) {
+        let metadata = self.mods.entry(key(id)).or_default();
+        metadata.installed_at = Some(source.installed_at);
+        metadata.source = Some(source);
+    }
+
+    /// gives mods svmm hasn't seen before an install date, returns whether anything changed
+    pub fn record_installs(&mut self, mods: &[InstalledMod]) -> bool {
+        let mut changed = false;
+        // broken mods are keyed by folder, their metadata would stick around after they get fixed or deleted
+        for imod in mods.iter().filter(|imod| !is_placeholder(&imod.manifest.unique_id)) {
+            let metadata = self.mods.entry(key(&imod.manifest.unique_id)).or_default();
+            if metadata.installed_at.is_none() {
+                // the manifest is written when the mod gets extracted, close enough for mods from before svmm
+                let installed_at = imod
+                    .modified
+                    .duration_since(UNIX_EPOCH)
+                    .map(|duration| duration.as_secs())
+                    .unwrap_or_default();
+                metadata.installed_at = Some(installed_at);
+                changed = true;
+            }
+        }
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use super::*;
+    use crate::{diagnostics::placeholder_manifest, ModManifest};
+
+    fn installed(manifest: ModManifest, modified: u64) -> InstalledMod {
+        InstalledMod {
+            path: PathBuf::from(&manifest.name),
+            active: true,
+            modified: SystemTime::UNIX_EPOCH + Duration::from_secs(modified),
+            manifest,
+            group: None,
+            diagnostics: vec![],
+        }
+    }
+
+    fn manifest(id: &str) -> ModManifest {
+        ModManifest {
+            name: id.to_owned(),
+            unique_id: id.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn saved_metadata_loads_back_by_any_casing() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("svmm").join("metadata.ron");
+
+        let mut store = MetadataStore::load(path.clone());
+        assert_eq!(store.get("Me.Mod"), None);
+        let metadata = ModMetadata {
+            tags: vec!["QoL".to_owned()],
+            notes: "keep".to_owned(),
+            pinned: true,
+            ..Default::default()
+        };
+        store.set(" Me.Mod", metadata.clone());
+        store.save().unwrap();
+
+        let store = MetadataStore::load(path);
+        assert_eq!(store.get("me.mod"), Some(&metadata));
+        assert_eq!(store.tags(), ["QoL"]);
+    }
+
+    #[test]
+    fn corrupt_metadata_is_moved_aside() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("metadata.ron");
+        write(&path, "{ not ron").unwrap();
+
+        let store = MetadataStore::load(path.clone());
+        assert_eq!(store.get("Me.Mod"), None);
+        assert!(!path.exists());
+        assert_eq!(read_to_string(path.with_extension("ron.corrupt")).unwrap(), "{ not ron");
+
+        // the next save doesn't touch the backup
+        store.save().unwrap();
+        assert!(path.exists());
+        assert_eq!(read_to_string(path.with_extension("ron.corrupt")).unwrap(), "{ not ron");
+    }
+
+    #[test]
+    fn installs_are_recorded_once_and_placeholders_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = MetadataStore::load(dir.path().join("metadata.ron"));
+        let mods = [
+            installed(manifest("Me.Mod"), 100),
+            installed(placeholder_manifest("Broken"), 100),
+        ];
+
+        assert!(store.record_installs(&mods));
+        assert_eq!(store.get("Me.Mod").unwrap().installed_at, Some(100));
+        assert_eq!(store.get("broken:Broken"), None);
+
+        // a later scan keeps the first date
+        let mods = [installed(manifest("Me.Mod"), 200)];
+        assert!(!store.record_installs(&mods));
+        assert_eq!(store.get("Me.Mod").unwrap().installed_at, Some(100));
+        assert!(!store.record_installs(&[installed(placeholder_manifest("Broken"), 100)]));
+    }
+}
@@ -0,0 +1,226 @@
+use std::{
+    cmp::Reverse,
+    fs::{copy, create_dir_all, read_dir, remove_dir_all},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use color_eyre::Result;
+use log::{debug, info};
+
+use crate::{history::rename_logged, InstalledMod};
+
+/// a version of a mod that got replaced, stored as `versions/<unique id>/<millis>-<version>/<folder>`
+#[derive(Clone, Debug)]
+pub struct StoredVersion {
+    pub version: String,
+    /// unix millis of when it was replaced
+    pub stored_at: u128,
+    /// the mod folder itself
+    pub path: PathBuf,
+}
+
+pub struct VersionStore {
+    path: PathBuf,
+    limit: usize,
+}
+
+/// keeps ids and versions usable as folder names on every platform
+fn folder_name(text: &str) -> String {
+    text.trim()
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || "._+-".contains(c) {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+impl VersionStore {
+    pub fn new(path: PathBuf, limit: usize) -> Self {
+        Self { path, limit }
+    }
+
+    fn mod_dir(&self, id: &str) -> PathBuf {
+        self.path.join(folder_name(&id.to_ascii_lowercase()))
+    }
+
+    /// moves the folder of `imod` into the store and returns where it ended up
+    pub fn archive(&self, imod: &InstalledMod) -> Result<PathBuf> {
+        let millis = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis();
+        let dir = self
+            .mod_dir(&imod.manifest.unique_id)
+            .join(format!("{millis}-{}", folder_name(&imod.manifest.version)));
+        create_dir_all(&dir)?;
+
+        let target = dir.join(imod.path.file_name().unwrap());
+        info!(
+            "Storing {} {} as {target:?}",
+            imod.manifest.unique_id, imod.manifest.version
+        );
+        rename_logged(&imod.path, &target)?;
+        Ok(target)
+    }
+
+    /// every stored version of `id`, newest first
+    pub fn list(&self, id: &str) -> Result<Vec<StoredVersion>> {
+        let dir = self.mod_dir(id);
+        if !dir.try_exists()? {
+            return Ok(vec![]);
+        }
+
+        let mut versions = vec![];
+        for entry in read_dir(dir)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            let Some((millis, version)) = name.split_once('-') else {
+                continue;
+            };
+            let Ok(stored_at) = millis.parse() else {
+                continue;
+            };
+            // empty after rolling back to it
+            let Some(folder) = read_dir(entry.path())?.next() else {
+                continue;
+            };
+            versions.push(StoredVersion {
+                version: version.to_owned(),
+                stored_at,
+                path: folder?.path(),
+            });
+        }
+        versions.sort_by_key(|version| Reverse(version.stored_at));
+        Ok(versions)
+    }
+
+    /// deletes the oldest versions of `id` over the limit for good, returns the folders that are gone so the history
+    /// can forget the steps that need them
+    pub fn prune(&self, id: &str) -> Result<Vec<PathBuf>> {
+        let mut removed = vec![];
+        for version in self.list(id)?.into_iter().skip(self.limit) {
+            info!("Dropping stored version {} of {id}", version.version);
+            let dir = version.path.parent().unwrap().to_path_buf();
+            remove_dir_all(&dir)?;
+            removed.push(dir);
+        }
+        Ok(removed)
+    }
+}
+
+/// copies the users `config.json` over from the replaced version, mods write it on first launch so archives don't
+/// ship one
+pub fn keep_config(old: &Path, new: &Path) -> Result<()> {
+    let config = old.join("config.json");
+    if config.try_exists()? && new.is_dir() {
+        debug!("Keeping {config:?}");
+        copy(config, new.join("config.json"))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{read_to_string, rename, write};
+
+    use serde_json::json;
+
+    use super::*;
+
+    /// a mod folder called `folder` inside `dir` with `version` in its manifest
+    fn write_mod(dir: &Path, folder: &str, version: &str) -> InstalledMod {
+        let path = dir.join(folder);
+        create_dir_all(&path).unwrap();
+        let manifest = json!({"Name": folder, "Author": "someone", "Version": version, "UniqueID": "Someone.Mod"});
+        write(path.join("manifest.json"), manifest.to_string()).unwrap();
+        InstalledMod {
+            path,
+            active: true,
+            modified: SystemTime::UNIX_EPOCH,
+            manifest: serde_json::from_value(manifest).unwrap(),
+            group: None,
+            diagnostics: vec![],
+        }
+    }
+
+    fn versions(store: &VersionStore) -> Vec<String> {
+        store
+            .list("someone.mod")
+            .unwrap()
+            .into_iter()
+            .map(|version| version.version)
+            .collect()
+    }
+
+    #[test]
+    fn archived_versions_can_be_rolled_back_to() {
+        let dir = tempfile::tempdir().unwrap();
+        let mods = dir.path().join("Mods");
+        let store = VersionStore::new(dir.path().join("versions"), 5);
+
+        let old = write_mod(&mods, "Mod", "1.0.0");
+        write(old.path.join("config.json"), "{\"Speed\": 2}").unwrap();
+        let stored = store.archive(&old).unwrap();
+        assert!(!old.path.exists());
+        assert_eq!(stored.file_name().unwrap(), "Mod");
+        assert!(stored.join("manifest.json").is_file());
+
+        // the new version comes without the config the user made
+        let new = write_mod(&mods, "Mod", "1.1.0");
+        keep_config(&stored, &new.path).unwrap();
+        assert_eq!(read_to_string(new.path.join("config.json")).unwrap(), "{\"Speed\": 2}");
+        assert_eq!(versions(&store), ["1.0.0"]);
+
+        // rolling back stores the newer version and empties the old slot, which the list skips
+        let listed = store.list("Someone.Mod").unwrap();
+        let replaced = store.archive(&new).unwrap();
+        rename(&listed[0].path, &new.path).unwrap();
+        keep_config(&replaced, &new.path).unwrap();
+        assert_eq!(versions(&store), ["1.1.0"]);
+        assert!(read_to_string(new.path.join("manifest.json"))
+            .unwrap()
+            .contains("1.0.0"));
+    }
+
+    #[test]
+    fn pruning_keeps_the_newest_versions() {
+        let dir = tempfile::tempdir().unwrap();
+        let mods = dir.path().join("Mods");
+        let store = VersionStore::new(dir.path().join("versions"), 2);
+
+        let mut stored = vec![];
+        for version in ["1.0.0", "1.1.0", "1.2.0", "2.0.0"] {
+            stored.push(store.archive(&write_mod(&mods, "Mod", version)).unwrap());
+            // versions are told apart by the millis they were stored at
+            std::thread::sleep(std::time::Duration::from_millis(2));
+        }
+        assert_eq!(versions(&store), ["2.0.0", "1.2.0", "1.1.0", "1.0.0"]);
+
+        let removed = store.prune("Someone.Mod").unwrap();
+        assert_eq!(versions(&store), ["2.0.0", "1.2.0"]);
+        assert_eq!(removed.len(), 2);
+        assert!(removed.iter().all(|dir| !dir.exists()));
+        assert!(stored[..2]
+            .iter()
+            .all(|path| removed.contains(&path.parent().unwrap().to_path_buf())));
+
+        // nothing over the limit, nothing to do
+        assert!(store.prune("Someone.Mod").unwrap().is_empty());
+        assert!(store.list("Other.Mod").unwrap().is_empty());
+    }
+
+    #[test]
+    fn keep_config_needs_a_config_and_a_target() {
+        let dir = tempfile::tempdir().unwrap();
+        let old = write_mod(dir.path(), "Old", "1.0.0");
+        let new = write_mod(dir.path(), "New", "1.1.0");
+        keep_config(&old.path, &new.path).unwrap();
+        assert!(!new.path.join("config.json").exists());
+
+        write(old.path.join("config.json"), "{}").unwrap();
+        keep_config(&old.path, &dir.path().join("Missing")).unwrap();
+        assert!(!dir.path().join("Missing").exists());
+    }
+}
@@ -0,0 +1,216 @@
+use std::cmp::Reverse;
+
+use color_eyre::{eyre::eyre, Result};
+use once_cell::sync::Lazy;
+use reqwest::Url;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{config::SVMMConfig, i18n::tr};
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct NexusMod {
+    #[serde(default)]
+    pub mod_id: u32,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub summary: String,
+    #[serde(default)]
+    pub version: String,
+    #[serde(default)]
+    pub author: String,
+    #[serde(default)]
+    pub endorsement_count: u32,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct NexusFile {
+    #[serde(default)]
+    pub file_id: u64,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub version: String,
+    #[serde(default)]
+    pub category_name: Option<String>,
+    #[serde(default)]
+    pub size_kb: u64,
+    #[serde(default)]
+    pub uploaded_timestamp: i64,
+    #[serde(default)]
+    pub file_name: String,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+struct NexusFiles {
+    #[serde(default)]
+    files: Vec<NexusFile>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+struct NexusDownloadLink {
+    #[serde(rename = "URI")]
+    uri: String,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct NexusModDetails {
+    pub info: NexusMod,
+    pub files: Vec<NexusFile>,
+}
+
+const NEXUS_API_URL: &str = "https://api.nexusmods.com/v1";
+const NEXUS_GAME: &str = "stardewvalley";
+
+static CLIENT: Lazy<reqwest::Client> = Lazy::new(|| {
+    reqwest::Client::builder()
+        .user_agent("StardewValleyModManager (https://github.com/tricked/stardew-valley-mod-manager)")
+        .build()
+        .unwrap()
+});
+
+#[derive(Clone, Debug)]
+pub struct NexusClient {
+    base_url: String,
+    api_key: String,
+}
+
+impl NexusClient {
+    pub fn new(base_url: Option<&str>, api_key: &str) -> Self {
+        Self {
+            base_url: base_url.unwrap_or(NEXUS_API_URL).trim_end_matches('/').to_owned(),
+            api_key: api_key.to_owned(),
+        }
+    }
+
+    /// returns `None` when the user hasn't configured an api key
+    pub fn from_config(config: &SVMMConfig) -> Option<Self> {
+        let api_key = config.nexus_api_key.as_deref().filter(|key| !key.trim().is_empty())?;
+        Some(Self::new(config.nexus_api_url.as_deref(), api_key.trim()))
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        self.get_with_query(path, &[]).await
+    }
+
+    async fn get_with_query<T: DeserializeOwned>(&self, path: &str, query: &[(&str, String)]) -> Result<T> {
+        let result = CLIENT
+            .get(format!("{}/games/{NEXUS_GAME}/{path}", self.base_url))
+            .query(query)
+            .header("apikey", &self.api_key)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<T>()
+            .await?;
+
+        Ok(result)
+    }
+
+    pub async fn get_mod(&self, mod_id: u32) -> Result<NexusMod> {
+        self.get(&format!("mods/{mod_id}.json")).await
+    }
+
+    pub async fn get_files(&self, mod_id: u32) -> Result<Vec<NexusFile>> {
+        Ok(self
+            .get::<NexusFiles>(&format!("mods/{mod_id}/files.json"))
+            .await?
+            .files)
+    }
+
+    /// non premium users need the `key` and `expires` from the nxm link the website handed out
+    pub async fn get_download_link(
+        &self,
+        mod_id: u32,
+        file_id: u64,
+        key: Option<String>,
+        expires: Option<String>,
+    ) -> Result<Url> {
+        let query: Vec<_> = [("key", key), ("expires", expires)]
+            .into_iter()
+            .filter_map(|(name, value)| Some((name, value?)))
+            .collect();
+
+        let links: Vec<NexusDownloadLink> = self
+            .get_with_query(&format!("mods/{mod_id}/files/{file_id}/download_link.json"), &query)
+            .await?;
+
+        let link = links.first().ok_or(eyre!(tr!("Nexus returned no download links")))?;
+        Ok(Url::parse(&link.uri)?)
+    }
+
+    pub async fn get_mod_details(&self, mod_id: u32) -> Result<NexusModDetails> {
+        let (info, mut files) = futures::try_join!(self.get_mod(mod_id), self.get_files(mod_id))?;
+        // newest uploads first
+        files.sort_by_key(|file| Reverse(file.uploaded_timestamp));
+        Ok(NexusModDetails { info, files })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::{serve, Response};
+
+    #[tokio::test]
+    async fn parses_mod_details_from_a_stand_in_server() {
+        let url = serve(|request| {
+            if request.header("apikey") != Some("secret") {
+                return Response::new(401, r#"{"message":"Please provide a valid API Key"}"#);
+            }
+            match request.path.as_str() {
+                "/v1/games/stardewvalley/mods/2400.json" => Response::new(
+                    200,
+                    r#"{"mod_id":2400,"name":"Stardew Valley Expanded","summary":"A fan-made expansion",
+                        "version":"1.14.46","author":"FlashShifter","endorsement_count":210000,"unknown":true}"#,
+                )
+                .header("Content-Type", "application/json"),
+                "/v1/games/stardewvalley/mods/2400/files.json" => Response::new(
+                    200,
+                    r#"{"files":[
+                        {"file_id":1,"name":"Old","version":"1.0","category_name":"OLD_VERSION","size_kb":10,
+                         "uploaded_timestamp":100,"file_name":"old.zip"},
+                        {"file_id":2,"name":"Main","version":"1.14.46","category_name":"MAIN","size_kb":20,
+                         "uploaded_timestamp":200,"file_name":"main.zip"},
+                        {"file_id":3,"uploaded_timestamp":150}
+                    ],"file_updates":[]}"#,
+                ),
+                _ => Response::new(404, "{}"),
+            }
+        })
+        .await;
+
+        let client = NexusClient::new(Some(&format!("{url}/v1/")), "secret");
+        let details = client.get_mod_details(2400).await.unwrap();
+        assert_eq!(details.info.name, "Stardew Valley Expanded");
+        assert_eq!(details.info.endorsement_count, 210000);
+        let ids: Vec<_> = details.files.iter().map(|file| file.file_id).collect();
+        assert_eq!(ids, [2, 3, 1]);
+        assert_eq!(details.files[0].category_name.as_deref(), Some("MAIN"));
+        assert_eq!(details.files[1].file_name, "");
+
+        let unauthorized = NexusClient::new(Some(&format!("{url}/v1")), "wrong");
+        assert!(unauthorized.get_mod(2400).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn download_links_pass_the_nxm_key_along() {
+        let url = serve(|request| match request.path.as_str() {
+            "/games/stardewvalley/mods/1/files/2/download_link.json?key=abc&expires=99" => Response::new(
+                200,
+                r#"[{"name":"Nexus CDN","short_name":"CDN","URI":"https://cdn.example/file.zip"}]"#,
+            ),
+            "/games/stardewvalley/mods/1/files/3/download_link.json" => Response::new(200, "[]"),
+            _ => Response::new(403, "{}"),
+        })
+        .await;
+
+        let client = NexusClient::new(Some(&url), "secret");
+        let link = client
+            .get_download_link(1, 2, Some("abc".into()), Some("99".into()))
+            .await
+            .unwrap();
+        assert_eq!(link.as_str(), "https://cdn.example/file.zip");
+        assert!(client.get_download_link(1, 3, None, None).await.is_err());
+    }
+}
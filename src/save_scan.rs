@@ -0,0 +1,148 @@
+use std::{
+    collections::HashMap,
+    fs::read_to_string,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
+use color_eyre::Result;
+use log::debug;
+use once_cell::sync::Lazy;
+
+/// the mod qualified ids in a save, keyed by lowercase prefix like `author.modname` with how often they show up
+#[derive(Clone, Debug, Default)]
+pub struct SaveScan {
+    prefixes: HashMap<String, usize>,
+    /// lowercase `xsi:type` values like `mods_spacechase0_dynamicgameassets_customobject`, spacecore writes custom
+    /// classes that way
+    types: HashMap<String, usize>,
+}
+
+/// ids are `{{ModId}}_Name` since 1.6, optionally qualified like `(O)`. Every prefix ending before an underscore
+/// that looks like a unique id counts, the comparison with the installed mods sorts out the rest
+fn record_value(value: &str, prefixes: &mut HashMap<String, usize>) {
+    let value = match value.strip_prefix('(').and_then(|rest| rest.split_once(')')) {
+        Some((qualifier, id)) if qualifier.chars().all(|c| c.is_ascii_uppercase()) => id,
+        _ => value,
+    };
+    if value.len() > 200 || value.contains(char::is_whitespace) || value.contains(['/', '\\']) {
+        return;
+    }
+    for (index, _) in value.match_indices('_') {
+        let prefix = &value[..index];
+        if prefix.contains('.') && prefix.contains(char::is_alphabetic) {
+            *prefixes.entry(prefix.to_ascii_lowercase()).or_default() += 1;
+        }
+    }
+}
+
+impl SaveScan {
+    pub fn parse(xml: &str) -> Self {
+        let mut scan = SaveScan::default();
+        for chunk in xml.split('<') {
+            let Some((tag, text)) = chunk.split_once('>') else {
+                continue;
+            };
+            if let Some((_, rest)) = tag.split_once("xsi:type=\"") {
+                let value = rest.split('"').next().unwrap_or_default();
+                if value.starts_with("Mods_") {
+                    *scan.types.entry(value.to_ascii_lowercase()).or_default() += 1;
+                }
+            }
+            let text = text.trim();
+            if !text.is_empty() {
+                record_value(text, &mut scan.prefixes);
+            }
+        }
+        scan
+    }
+
+    /// how many ids and custom classes of the mod with `unique_id` the save contains
+    pub fn references(&self, unique_id: &str) -> usize {
+        let id = unique_id.trim().to_ascii_lowercase();
+        let type_prefix = format!("mods_{}_", id.replace('.', "_"));
+        let types: usize = self
+            .types
+            .iter()
+            .filter(|(value, _)| value.starts_with(&type_prefix))
+            .map(|(_, count)| count)
+            .sum();
+        self.prefixes.get(&id).copied().unwrap_or_default() + types
+    }
+}
+
+/// save file to when it was scanned and what was found
+type ScanCache = HashMap<PathBuf, (SystemTime, Arc<SaveScan>)>;
+
+/// saves are tens of megabytes, only scan them again when the game wrote them
+static CACHE: Lazy<Mutex<ScanCache>> = Lazy::new(Default::default);
+
+/// scans the main save file `save_file`, `Saves/<folder>/<folder>`
+pub fn scan_save(save_file: &Path) -> Result<Arc<SaveScan>> {
+    let modified = save_file.metadata()?.modified()?;
+    if let Some((scanned, scan)) = CACHE.lock().unwrap().get(save_file) {
+        if *scanned == modified {
+            return Ok(scan.clone());
+        }
+    }
+
+    debug!("Scanning {save_file:?} for mod ids");
+    let scan = Arc::new(SaveScan::parse(&read_to_string(save_file)?));
+    CACHE
+        .lock()
+        .unwrap()
+        .insert(save_file.to_path_buf(), (modified, scan.clone()));
+    Ok(scan)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAVE: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<SaveGame xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
+  <player>
+    <name>Farmer</name>
+    <items>
+      <Item xsi:type="Object"><itemId>(O)FlashShifter.StardewValleyExpandedCP_Fir_Wax</itemId></Item>
+      <Item xsi:type="Object"><itemId>FlashShifter.StardewValleyExpandedCP_Birch_Syrup</itemId></Item>
+      <Item xsi:type="Mods_spacechase0_DynamicGameAssets_CustomObject"><name>Thing</name></Item>
+      <Item xsi:type="Object"><itemId>(BC)130</itemId></Item>
+    </items>
+    <mailReceived>
+      <string>Cornucopia.MoreCrops_Seen_Letter</string>
+      <string>not an id_with underscores</string>
+      <string>1.6_update</string>
+      <string>ccBulletin_Done</string>
+    </mailReceived>
+  </player>
+</SaveGame>"#;
+
+    #[test]
+    fn counts_qualified_and_plain_ids() {
+        let scan = SaveScan::parse(SAVE);
+        assert_eq!(scan.references("FlashShifter.StardewValleyExpandedCP"), 2);
+        // unique ids are case insensitive
+        assert_eq!(scan.references("flashshifter.stardewvalleyexpandedcp"), 2);
+        assert_eq!(scan.references("Pathoschild.ContentPatcher"), 0);
+    }
+
+    #[test]
+    fn every_prefix_before_an_underscore_counts() {
+        let scan = SaveScan::parse(SAVE);
+        assert_eq!(scan.references("Cornucopia.MoreCrops"), 1);
+        assert_eq!(scan.references("Cornucopia.MoreCrops_Seen"), 1);
+        // prefixes need a dot and a letter, text with spaces isn't an id
+        assert_eq!(scan.references("1.6"), 0);
+        assert_eq!(scan.references("ccBulletin"), 0);
+        assert_eq!(scan.references("not an id"), 0);
+    }
+
+    #[test]
+    fn counts_custom_classes() {
+        let scan = SaveScan::parse(SAVE);
+        assert_eq!(scan.references("spacechase0.DynamicGameAssets"), 1);
+        assert_eq!(scan.references("spacechase0.Dynamic"), 0);
+    }
+}
@@ -0,0 +1,314 @@
+use std::{
+    collections::HashMap,
+    fs::{copy, create_dir_all, read_dir, read_to_string, remove_dir_all, write},
+    path::{Path, PathBuf},
+};
+
+use color_eyre::Result;
+use log::{debug, info, warn};
+use time::{macros::format_description, OffsetDateTime};
+use walkdir::WalkDir;
+
+use crate::i18n::tr;
+
+/// a save folder and what its `SaveGameInfo` says about it
+#[derive(Clone, Debug)]
+pub struct SaveGame {
+    /// the folder name, like `Tricked_123456789`, unique per save
+    pub folder: String,
+    pub path: PathBuf,
+    pub farmer: String,
+    pub farm: String,
+    pub day: u32,
+    /// 0 to 3, spring to winter
+    pub season: u32,
+    pub year: u32,
+    pub played_ms: u64,
+}
+
+impl SaveGame {
+    /// `Summer 12, year 2`
+    pub fn date(&self) -> String {
+        let season = match self.season {
+            0 => tr!("Spring"),
+            1 => tr!("Summer"),
+            2 => tr!("Fall"),
+            3 => tr!("Winter"),
+            _ => tr!("Unknown season"),
+        };
+        tr!("{} {}, year {}", season, self.day, self.year)
+    }
+
+    /// `12h 05m`
+    pub fn play_time(&self) -> String {
+        let minutes = self.played_ms / 60_000;
+        tr!("{}h {}m", minutes / 60, format!("{:02}", minutes % 60))
+    }
+}
+
+/// where the game keeps its saves, it uses `~/.config` on macos too
+pub fn default_saves_path() -> Option<PathBuf> {
+    let config_dir = if cfg!(target_os = "macos") {
+        dirs::home_dir().map(|home| home.join(".config"))
+    } else {
+        dirs::config_dir()
+    };
+    config_dir.map(|dir| dir.join("StardewValley").join("Saves"))
+}
+
+/// the text of the first `<tag>` in `xml`. `SaveGameInfo` is a serialized farmer whose own fields come before any
+/// nested ones, so the first match is the right one for the fields we read
+fn xml_value<'a>(xml: &'a str, tag: &str) -> Option<&'a str> {
+    let start = xml.find(&format!("<{tag}>"))? + tag.len() + 2;
+    let end = xml[start..].find(&format!("</{tag}>"))? + start;
+    Some(&xml[start..end])
+}
+
+fn read_save(path: &Path) -> Result<SaveGame> {
+    let xml = read_to_string(path.join("SaveGameInfo"))?;
+    let number = |tag: &str| xml_value(&xml, tag).and_then(|value| value.trim().parse().ok());
+    Ok(SaveGame {
+        folder: path.file_name().unwrap().to_string_lossy().to_string(),
+        path: path.to_path_buf(),
+        farmer: xml_value(&xml, "name").unwrap_or_default().to_owned(),
+        farm: xml_value(&xml, "farmName").unwrap_or_default().to_owned(),
+        day: number("dayOfMonthForSaveGame").unwrap_or(1),
+        season: number("seasonForSaveGame").unwrap_or_default(),
+        year: number("yearForSaveGame").unwrap_or(1),
+        played_ms: xml_value(&xml, "millisecondsPlayed")
+            .and_then(|value| value.trim().parse().ok())
+            .unwrap_or_default(),
+    })
+}
+
+/// every folder in `saves_path` with a readable `SaveGameInfo`, sorted by folder name
+pub fn find_saves(saves_path: &Path) -> Result<Vec<SaveGame>> {
+    if !saves_path.try_exists()? {
+        debug!("No saves folder at {saves_path:?}");
+        return Ok(vec![]);
+    }
+
+    let mut saves = vec![];
+    for entry in read_dir(saves_path)? {
+        let path = entry?.path();
+        if !path.join("SaveGameInfo").is_file() {
+            continue;
+        }
+        match read_save(&path) {
+            Ok(save) => saves.push(save),
+            Err(err) => warn!("Skipping save {path:?}: {err}"),
+        }
+    }
+    saves.sort_by(|a, b| a.folder.cmp(&b.folder));
+    Ok(saves)
+}
+
+/// which profile each save is played with, stored as ron in the svmm folder
+#[derive(Clone, Debug, Default)]
+pub struct SaveBindings {
+    path: PathBuf,
+    saves: HashMap<String, String>,
+}
+
+impl SaveBindings {
+    pub fn load(path: PathBuf) -> Self {
+        let saves = match read_to_string(&path) {
+            Ok(content) => ron::from_str(&content).unwrap_or_else(|err| {
+                warn!("Ignoring corrupt save bindings {path:?}: {err}");
+                Default::default()
+            }),
+            Err(_) => Default::default(),
+        };
+        Self { path, saves }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        write(&self.path, ron::ser::to_string_pretty(&self.saves, Default::default())?)?;
+        Ok(())
+    }
+
+    pub fn get(&self, folder: &str) -> Option<&String> {
+        self.saves.get(folder)
+    }
+
+    /// `None` unbinds the save
+    pub fn set(&mut self, folder: &str, profile: Option<String>) {
+        match profile {
+            Some(profile) => self.saves.insert(folder.to_owned(), profile),
+            None => self.saves.remove(folder),
+        };
+    }
+
+    /// saves bound to one of `profiles`, unbound saves can be played with any profile so they always count
+    pub fn affected<'a>(&self, saves: &'a [SaveGame], profiles: &[&str]) -> Vec<&'a SaveGame> {
+        saves
+            .iter()
+            .filter(|save| {
+                self.get(&save.folder)
+                    .is_none_or(|profile| profiles.contains(&profile.as_str()))
+            })
+            .collect()
+    }
+}
+
+/// rotating copies of save folders as `save-backups/<save>/<timestamp>`
+pub struct SaveBackups {
+    path: PathBuf,
+    limit: usize,
+}
+
+impl SaveBackups {
+    pub fn new(path: PathBuf, limit: usize) -> Self {
+        Self { path, limit }
+    }
+
+    /// timestamps of the backups of `folder`, newest first
+    pub fn list(&self, folder: &str) -> Result<Vec<String>> {
+        let dir = self.path.join(folder);
+        if !dir.try_exists()? {
+            return Ok(vec![]);
+        }
+        let mut backups: Vec<String> = read_dir(dir)?
+            .flatten()
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .collect();
+        // the timestamps sort like dates
+        backups.sort_by(|a, b| b.cmp(a));
+        Ok(backups)
+    }
+
+    /// copies `save` into a new backup and drops the oldest ones over the limit
+    pub fn backup(&self, save: &SaveGame) -> Result<PathBuf> {
+        let now = OffsetDateTime::now_local().unwrap_or_else(|_| OffsetDateTime::now_utc());
+        let timestamp = now.format(format_description!("[year]-[month]-[day]_[hour]-[minute]-[second]"))?;
+        let target = self.path.join(&save.folder).join(timestamp);
+        if target.try_exists()? {
+            debug!("{target:?} was backed up a moment ago");
+            return Ok(target);
+        }
+
+        for entry in WalkDir::new(&save.path) {
+            let entry = entry?;
+            let destination = target.join(entry.path().strip_prefix(&save.path)?);
+            if entry.file_type().is_dir() {
+                create_dir_all(&destination)?;
+            } else {
+                copy(entry.path(), &destination)?;
+            }
+        }
+        info!("Backed up {} to {target:?}", save.folder);
+
+        for old in self.list(&save.folder)?.into_iter().skip(self.limit) {
+            debug!("Dropping backup {old} of {}", save.folder);
+            remove_dir_all(self.path.join(&save.folder).join(old))?;
+        }
+        Ok(target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// trimmed down from a real `SaveGameInfo`, the farmer's items have names too
+    const SAVE_GAME_INFO: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<Farmer xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
+  <name>Tricked</name>
+  <items>
+    <Item xsi:type="Object"><name>Parsnip</name></Item>
+  </items>
+  <farmName>Sunny</farmName>
+  <dayOfMonthForSaveGame>12</dayOfMonthForSaveGame>
+  <seasonForSaveGame>1</seasonForSaveGame>
+  <yearForSaveGame>2</yearForSaveGame>
+  <millisecondsPlayed>43500000</millisecondsPlayed>
+</Farmer>"#;
+
+    fn write_save(saves: &Path, folder: &str, info: &str) -> PathBuf {
+        let path = saves.join(folder);
+        create_dir_all(&path).unwrap();
+        write(path.join("SaveGameInfo"), info).unwrap();
+        write(path.join(folder), "<SaveGame />").unwrap();
+        path
+    }
+
+    #[test]
+    fn xml_value_reads_the_first_tag() {
+        assert_eq!(xml_value(SAVE_GAME_INFO, "name"), Some("Tricked"));
+        assert_eq!(xml_value(SAVE_GAME_INFO, "farmName"), Some("Sunny"));
+        assert_eq!(xml_value(SAVE_GAME_INFO, "missing"), None);
+        assert_eq!(xml_value("<name>unclosed", "name"), None);
+    }
+
+    #[test]
+    fn finds_readable_saves() {
+        let dir = tempfile::tempdir().unwrap();
+        write_save(dir.path(), "Tricked_2", SAVE_GAME_INFO);
+        write_save(dir.path(), "Empty_1", "<Farmer />");
+        create_dir_all(dir.path().join("NotASave")).unwrap();
+        write(dir.path().join("steam_autocloud.vdf"), "").unwrap();
+
+        let saves = find_saves(dir.path()).unwrap();
+        let folders: Vec<_> = saves.iter().map(|save| save.folder.as_str()).collect();
+        assert_eq!(folders, ["Empty_1", "Tricked_2"]);
+
+        let save = &saves[1];
+        assert_eq!((save.farmer.as_str(), save.farm.as_str()), ("Tricked", "Sunny"));
+        assert_eq!((save.day, save.season, save.year), (12, 1, 2));
+        assert_eq!(save.date(), "Summer 12, year 2");
+        assert_eq!(save.play_time(), "12h 05m");
+
+        // missing fields fall back to the first day
+        assert_eq!((saves[0].day, saves[0].season, saves[0].year), (1, 0, 1));
+        assert!(find_saves(&dir.path().join("missing")).unwrap().is_empty());
+    }
+
+    #[test]
+    fn unbound_saves_are_always_affected() {
+        let dir = tempfile::tempdir().unwrap();
+        for folder in ["A_1", "B_2", "C_3"] {
+            write_save(dir.path(), folder, SAVE_GAME_INFO);
+        }
+        let saves = find_saves(dir.path()).unwrap();
+
+        let mut bindings = SaveBindings::load(dir.path().join("saves.ron"));
+        bindings.set("A_1", Some("Vanilla".to_owned()));
+        bindings.set("B_2", Some("Expanded".to_owned()));
+        bindings.save().unwrap();
+        let bindings = SaveBindings::load(dir.path().join("saves.ron"));
+
+        let affected = |profiles: &[&str]| -> Vec<String> {
+            bindings
+                .affected(&saves, profiles)
+                .into_iter()
+                .map(|save| save.folder.clone())
+                .collect()
+        };
+        assert_eq!(affected(&["Vanilla"]), ["A_1", "C_3"]);
+        assert_eq!(affected(&["Vanilla", "Expanded"]), ["A_1", "B_2", "C_3"]);
+        assert_eq!(affected(&[]), ["C_3"]);
+    }
+
+    #[test]
+    fn backups_rotate_at_the_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_save(&dir.path().join("Saves"), "Tricked_1", SAVE_GAME_INFO);
+        let save = &find_saves(&dir.path().join("Saves")).unwrap()[0];
+        let backups = SaveBackups::new(dir.path().join("save-backups"), 3);
+
+        // older backups from earlier launches
+        for old in ["2020-01-01_00-00-00", "2021-01-01_00-00-00", "2022-01-01_00-00-00"] {
+            create_dir_all(dir.path().join("save-backups").join("Tricked_1").join(old)).unwrap();
+        }
+
+        let target = backups.backup(save).unwrap();
+        assert_eq!(read_to_string(target.join("SaveGameInfo")).unwrap(), SAVE_GAME_INFO);
+        assert!(target.join("Tricked_1").is_file());
+        let listed = backups.list("Tricked_1").unwrap();
+        assert_eq!(listed.len(), 3);
+        assert_eq!(listed[0], target.file_name().unwrap().to_string_lossy());
+        assert_eq!(listed[1..], ["2022-01-01_00-00-00", "2021-01-01_00-00-00"]);
+        assert!(path.is_dir());
+        assert!(backups.list("Other_2").unwrap().is_empty());
+    }
+}
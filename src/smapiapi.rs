@@ -1,9 +1,18 @@
-use std::env::consts::OS;
+use std::{
+    collections::HashMap,
+    env::consts::OS,
+    fs::{create_dir_all, read_to_string, write},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
-use color_eyre::Result;
+use color_eyre::{eyre::eyre, Result};
+use log::{debug, error, warn};
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 
+use crate::{config::SVMMConfig, i18n::tr};
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct SmapiRequest {
     platform: Option<String>,
@@ -17,6 +26,23 @@ pub struct SmapiMod {
     pub id: String,
     #[serde(default)]
     pub metadata: SmapiModMetadata,
+    #[serde(rename = "installedVersion")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub installed_version: Option<String>,
+    #[serde(rename = "updateKeys")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub update_keys: Vec<String>,
+    #[serde(rename = "suggestedUpdate")]
+    #[serde(default)]
+    pub suggested_update: Option<SmapiSuggestedUpdate>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct SmapiSuggestedUpdate {
+    #[serde(default)]
+    pub version: String,
+    #[serde(default)]
+    pub url: String,
 }
 
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
@@ -37,34 +63,404 @@ pub struct SmapiModMetadataMain {
 }
 
 const SMAPI_API_URL: &str = "https://smapi.io/api/v3.0/mods";
+/// smapi.io handles big requests fine but a single failure shouldn't lose every mod
+const BATCH_SIZE: usize = 100;
+const MAX_ATTEMPTS: u32 = 3;
+
+static CLIENT: Lazy<reqwest::Client> = Lazy::new(|| {
+    reqwest::Client::builder()
+        .user_agent("StardewValleyModManager (https://github.com/tricked/stardew-valley-mod-manager)")
+        .connect_timeout(Duration::from_secs(10))
+        .timeout(Duration::from_secs(30))
+        .build()
+        .unwrap()
+});
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct CacheEntry {
+    fetched_at: u64,
+    data: SmapiMod,
+}
+
+/// responses keyed by lowercase mod id, stored as json in the svmm folder
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+struct SmapiCache {
+    entries: HashMap<String, CacheEntry>,
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+}
+
+impl SmapiCache {
+    fn load(path: &Path) -> Self {
+        match read_to_string(path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|err| {
+                warn!("Ignoring corrupt smapi cache {path:?}: {err}");
+                Default::default()
+            }),
+            Err(_) => Default::default(),
+        }
+    }
+
+    fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            create_dir_all(parent)?;
+        }
+        write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    fn get(&self, id: &str, ttl: Option<Duration>) -> Option<&SmapiMod> {
+        let entry = self.entries.get(&id.to_ascii_lowercase())?;
+        match ttl {
+            Some(ttl) if now().saturating_sub(entry.fetched_at) > ttl.as_secs() => None,
+            _ => Some(&entry.data),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SmapiClient {
+    url: String,
+    cache_path: PathBuf,
+    ttl: Duration,
+}
+
+impl SmapiClient {
+    pub fn new(url: Option<&str>, cache_path: PathBuf, ttl: Duration) -> Self {
+        Self {
+            url: url.unwrap_or(SMAPI_API_URL).to_owned(),
+            cache_path,
+            ttl,
+        }
+    }
+
+    pub fn from_config(config: &SVMMConfig, svmm_path: &Path) -> Self {
+        Self::new(
+            config.smapi_api_url.as_deref(),
+            svmm_path.join("cache/smapi.json"),
+            Duration::from_secs(config.smapi_cache_ttl_hours.unwrap_or(24) * 60 * 60),
+        )
+    }
+
+    async fn post(&self, mods: Vec<SmapiMod>) -> Result<Vec<SmapiMod>> {
+        let request = SmapiRequest {
+            mods,
+            platform: match OS {
+                "linux" => Some("Linux".to_owned()),
+                "macos" => Some("Mac".to_owned()),
+                "windows" => Some("Windows".to_owned()),
+                "android" => Some("Android".to_owned()),
+                _ => None,
+            },
+            include_extended_metadata: true,
+        };
+
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let result = async {
+                CLIENT
+                    .post(&self.url)
+                    .json(&request)
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json::<Vec<SmapiMod>>()
+                    .await
+            }
+            .await;
+
+            match result {
+                Ok(result) => return Ok(result),
+                // client errors wont fix themselves
+                Err(err) if !err.status().is_some_and(|status| status.is_client_error()) && attempt < MAX_ATTEMPTS => {
+                    let backoff = Duration::from_millis(500 * 2u64.pow(attempt - 1));
+                    warn!("smapi.io request failed ({err}), retrying in {}ms", backoff.as_millis());
+                    tokio::time::sleep(backoff).await;
+                }
+                Err(err) => return Err(eyre!(tr!("Failed to reach {}: {}", self.url, err))),
+            }
+        }
+    }
 
-pub async fn resolve_mods(mods: Vec<SmapiMod>) -> Result<Vec<SmapiMod>> {
-    static CLIENT: Lazy<reqwest::Client> = Lazy::new(|| {
-        reqwest::Client::builder()
-            .user_agent("StardewValleyModManager (https://github.com/tricked/stardew-valley-mod-manager)")
-            .build()
-            .unwrap()
-    });
-
-    let request = SmapiRequest {
-        mods,
-        platform: match OS {
-            "linux" => Some("Linux".to_owned()),
-            "macos" => Some("Mac".to_owned()),
-            "windows" => Some("Windows".to_owned()),
-            "android" => Some("Android".to_owned()),
-            _ => None,
-        },
-        include_extended_metadata: true,
+    fn store(cache: &mut SmapiCache, resolved: &[SmapiMod]) {
+        for imod in resolved {
+            cache.entries.insert(
+                imod.id.to_ascii_lowercase(),
+                CacheEntry {
+                    fetched_at: now(),
+                    data: imod.clone(),
+                },
+            );
+        }
+    }
+
+    /// always asks smapi.io, mods need `installed_version` set for smapi to suggest updates
+    pub async fn check_updates(&self, mods: Vec<SmapiMod>) -> Result<Vec<SmapiMod>> {
+        let mut cache = SmapiCache::load(&self.cache_path);
+        let mut result = vec![];
+
+        for batch in mods.chunks(BATCH_SIZE) {
+            let resolved = self.post(batch.to_vec()).await?;
+            Self::store(&mut cache, &resolved);
+            result.extend(resolved);
+        }
+
+        if let Err(err) = cache.save(&self.cache_path) {
+            error!("Failed to save smapi cache: {err}");
+        }
+
+        Ok(result)
+    }
+
+    /// fresh cache entries are used as is, the rest is fetched in batches. When smapi.io can't be reached we fall
+    /// back to expired cache entries and finally to bare ids so the ui keeps working offline
+    pub async fn resolve_mods(&self, mods: Vec<SmapiMod>) -> Result<Vec<SmapiMod>> {
+        let mut cache = SmapiCache::load(&self.cache_path);
+
+        let (cached, missing): (Vec<_>, Vec<_>) = mods
+            .into_iter()
+            .partition(|imod| cache.get(&imod.id, Some(self.ttl)).is_some());
+        debug!("smapi cache hits: {} misses: {}", cached.len(), missing.len());
+
+        let mut result: Vec<SmapiMod> = cached
+            .iter()
+            .filter_map(|imod| cache.get(&imod.id, None).cloned())
+            .collect();
+
+        let mut offline = false;
+        for batch in missing.chunks(BATCH_SIZE) {
+            if offline {
+                result.extend(
+                    batch
+                        .iter()
+                        .map(|imod| cache.get(&imod.id, None).cloned().unwrap_or(imod.clone())),
+                );
+                continue;
+            }
+            match self.post(batch.to_vec()).await {
+                Ok(resolved) => {
+                    Self::store(&mut cache, &resolved);
+                    result.extend(resolved);
+                }
+                Err(err) => {
+                    error!("{err}, using cached data");
+                    offline = true;
+                    result.extend(
+                        batch
+                            .iter()
+                            .map(|imod| cache.get(&imod.id, None).cloned().unwrap_or(imod.clone())),
+                    );
+                }
+            }
+        }
+
+        if let Err(err) = cache.save(&self.cache_path) {
+            error!("Failed to save smapi cache: {err}");
+        }
+
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
     };
 
-    let result = CLIENT
-        .post(SMAPI_API_URL)
-        .json(&request)
-        .send()
-        .await?
-        .json::<Vec<SmapiMod>>()
-        .await?;
+    use super::*;
+    use crate::test_server::{serve, Request, Response};
+
+    const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+    fn mods(ids: impl IntoIterator<Item = String>) -> Vec<SmapiMod> {
+        ids.into_iter()
+            .map(|id| SmapiMod {
+                id,
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    /// answers with every requested mod, named after the server so tests can tell fresh data from cached data
+    fn answer(request: &Request, name: &str) -> Response {
+        let request: SmapiRequest = serde_json::from_slice(&request.body).unwrap();
+        let resolved: Vec<SmapiMod> = request
+            .mods
+            .into_iter()
+            .map(|imod| SmapiMod {
+                metadata: SmapiModMetadata {
+                    name: name.to_owned(),
+                    ..Default::default()
+                },
+                ..imod
+            })
+            .collect();
+        Response::new(200, serde_json::to_vec(&resolved).unwrap()).header("Content-Type", "application/json")
+    }
+
+    fn cache_with(path: &Path, entries: &[(&str, &str, u64)]) {
+        let mut cache = SmapiCache::default();
+        for (id, name, fetched_at) in entries {
+            cache.entries.insert(
+                id.to_ascii_lowercase(),
+                CacheEntry {
+                    fetched_at: *fetched_at,
+                    data: SmapiMod {
+                        id: id.to_string(),
+                        metadata: SmapiModMetadata {
+                            name: name.to_string(),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    },
+                },
+            );
+        }
+        cache.save(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn fresh_cache_entries_skip_the_request() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = dir.path().join("smapi.json");
+        cache_with(
+            &cache,
+            &[
+                ("Fresh.Mod", "cached", now()),
+                ("Stale.Mod", "cached", now() - 2 * DAY.as_secs()),
+            ],
+        );
+
+        let requested = Arc::new(Mutex::new(vec![]));
+        let url = serve({
+            let requested = requested.clone();
+            move |request| {
+                let body: SmapiRequest = serde_json::from_slice(&request.body).unwrap();
+                requested
+                    .lock()
+                    .unwrap()
+                    .extend(body.mods.into_iter().map(|imod| imod.id));
+                answer(request, "server")
+            }
+        })
+        .await;
+
+        let client = SmapiClient::new(Some(&url), cache.clone(), DAY);
+        let resolved = client
+            .resolve_mods(mods(["Fresh.Mod", "Stale.Mod"].map(str::to_owned)))
+            .await
+            .unwrap();
+        assert_eq!(*requested.lock().unwrap(), ["Stale.Mod"]);
+        let names: Vec<_> = resolved
+            .iter()
+            .map(|imod| (imod.id.as_str(), imod.metadata.name.as_str()))
+            .collect();
+        assert_eq!(names, [("Fresh.Mod", "cached"), ("Stale.Mod", "server")]);
+
+        // the stale entry got refreshed
+        let client = SmapiClient::new(Some(&url), cache, DAY);
+        client.resolve_mods(mods(["Stale.Mod".to_owned()])).await.unwrap();
+        assert_eq!(requested.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn requests_are_split_into_batches() {
+        let dir = tempfile::tempdir().unwrap();
+        let batches = Arc::new(Mutex::new(vec![]));
+        let url = serve({
+            let batches = batches.clone();
+            move |request| {
+                let body: SmapiRequest = serde_json::from_slice(&request.body).unwrap();
+                batches.lock().unwrap().push(body.mods.len());
+                answer(request, "server")
+            }
+        })
+        .await;
+
+        let client = SmapiClient::new(Some(&url), dir.path().join("smapi.json"), DAY);
+        let ids = (0..BATCH_SIZE * 2 + 50).map(|i| format!("Mod.{i}"));
+        let resolved = client.check_updates(mods(ids)).await.unwrap();
+        assert_eq!(resolved.len(), BATCH_SIZE * 2 + 50);
+        assert_eq!(*batches.lock().unwrap(), [BATCH_SIZE, BATCH_SIZE, 50]);
+    }
+
+    #[tokio::test]
+    async fn server_errors_are_retried() {
+        let dir = tempfile::tempdir().unwrap();
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let url = serve({
+            let attempts = attempts.clone();
+            move |request| match attempts.fetch_add(1, Ordering::SeqCst) {
+                0 => Response::new(502, "bad gateway"),
+                _ => answer(request, "server"),
+            }
+        })
+        .await;
+        let client = SmapiClient::new(Some(&url), dir.path().join("smapi.json"), DAY);
+        let resolved = client.check_updates(mods(["A".to_owned()])).await.unwrap();
+        assert_eq!(resolved[0].metadata.name, "server");
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+
+        // and given up on after MAX_ATTEMPTS
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let url = serve({
+            let attempts = attempts.clone();
+            move |_| {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                Response::new(503, "down")
+            }
+        })
+        .await;
+        let client = SmapiClient::new(Some(&url), dir.path().join("smapi.json"), DAY);
+        assert!(client.check_updates(mods(["A".to_owned()])).await.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), MAX_ATTEMPTS as usize);
+
+        // client errors won't fix themselves
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let url = serve({
+            let attempts = attempts.clone();
+            move |_| {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                Response::new(400, "bad request")
+            }
+        })
+        .await;
+        let client = SmapiClient::new(Some(&url), dir.path().join("smapi.json"), DAY);
+        assert!(client.check_updates(mods(["A".to_owned()])).await.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn offline_falls_back_to_stale_cache_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = dir.path().join("smapi.json");
+        cache_with(&cache, &[("Known.Mod", "cached", now() - 7 * DAY.as_secs())]);
+
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let url = serve({
+            let attempts = attempts.clone();
+            move |_| {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                Response::new(403, "blocked")
+            }
+        })
+        .await;
+        let client = SmapiClient::new(Some(&url), cache, DAY);
+        let ids = ["Known.Mod".to_owned(), "Unknown.Mod".to_owned()]
+            .into_iter()
+            .chain((0..BATCH_SIZE).map(|i| format!("Mod.{i}")));
+        let resolved = client.resolve_mods(mods(ids)).await.unwrap();
 
-    Ok(result)
+        assert_eq!(resolved.len(), BATCH_SIZE + 2);
+        assert_eq!(resolved[0].metadata.name, "cached");
+        assert_eq!(resolved[1].id, "Unknown.Mod");
+        assert_eq!(resolved[1].metadata.name, "");
+        // the second batch doesn't bother the server again
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
 }
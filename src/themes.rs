@@ -0,0 +1,153 @@
+use std::{
+    fs::{read_dir, read_to_string},
+    path::{Path, PathBuf},
+};
+
+use color_eyre::{eyre::eyre, Result};
+use log::{debug, warn};
+use serde::Deserialize;
+
+use crate::i18n::tr;
+
+/// the themes shipped with svmm by file name, `dark` is what svmm always looked like
+const BUILT_IN: &[(&str, &str)] = &[
+    ("dark", include_str!("../themes/dark.ron")),
+    ("light", include_str!("../themes/light.ron")),
+];
+
+/// a `#rgb`, `#rgba`, `#rrggbb` or `#rrggbbaa` color
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Color(pub slint::Color);
+
+impl TryFrom<String> for Color {
+    type Error = color_eyre::Report;
+
+    fn try_from(value: String) -> Result<Self> {
+        let hex = value
+            .strip_prefix('#')
+            .filter(|hex| hex.chars().all(|c| c.is_ascii_hexdigit()))
+            .ok_or_else(|| eyre!("{value:?} isn't a #rrggbb color"))?;
+        // short colors repeat every digit, #f0a is #ff00aa
+        let digits: Vec<u8> = match hex.len() {
+            3 | 4 => hex.chars().map(|c| c.to_digit(16).unwrap() as u8 * 17).collect(),
+            6 | 8 => (0..hex.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+                .collect(),
+            _ => return Err(eyre!("{value:?} isn't a #rrggbb color")),
+        };
+        let alpha = digits.get(3).copied().unwrap_or(255);
+        Ok(Color(slint::Color::from_argb_u8(
+            alpha, digits[0], digits[1], digits[2],
+        )))
+    }
+}
+
+/// the `Palette` of `ui/util.slint`, every color has to be set
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ThemePalette {
+    pub menu_bar: Color,
+    pub main_content: Color,
+    pub r#box: Color,
+    pub light_display: Color,
+    pub pie_chart: Color,
+    pub round_button: Color,
+    pub weekday_box: Color,
+    pub text: Color,
+    pub shadow: Color,
+    pub error: Color,
+    pub warning: Color,
+    pub success: Color,
+}
+
+impl ThemePalette {
+    /// whether the std widgets should use their dark style, going by how bright the main content is
+    pub fn is_dark(&self) -> bool {
+        let color = self.main_content.0;
+        let luminance = 0.299 * color.red() as f32 + 0.587 * color.green() as f32 + 0.114 * color.blue() as f32;
+        luminance < 128.0
+    }
+}
+
+/// font sizes in pixels
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ThemeFonts {
+    pub default: f32,
+    pub tiny: f32,
+    pub small: f32,
+    pub medium: f32,
+    pub large: f32,
+    pub huge: f32,
+    pub title: f32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Theme {
+    /// the file name without `.ron`, it's what gets remembered as the picked theme
+    #[serde(skip)]
+    pub id: String,
+    pub name: String,
+    pub palette: ThemePalette,
+    pub fonts: ThemeFonts,
+}
+
+/// where users put their own themes, next to `svmm.ron`
+pub fn user_themes_dir() -> PathBuf {
+    dirs::config_local_dir().unwrap().join("svmm").join("themes")
+}
+
+fn parse(id: &str, content: &str) -> Result<Theme> {
+    let mut theme: Theme = ron::from_str(content)?;
+    theme.id = id.to_owned();
+    Ok(theme)
+}
+
+/// the built in themes followed by the ones in `dir`, a user theme with the file name of a built in one replaces it.
+/// Broken files get logged and skipped so one typo doesn't take the other themes with it
+pub fn load_themes(dir: &Path) -> Vec<Theme> {
+    let mut themes: Vec<Theme> = BUILT_IN
+        .iter()
+        .map(|(id, content)| {
+            let mut theme = parse(id, content).expect("built in themes are valid");
+            theme.name = match *id {
+                "dark" => tr!("Dark"),
+                _ => tr!("Light"),
+            };
+            theme
+        })
+        .collect();
+
+    let Ok(entries) = read_dir(dir) else {
+        debug!("No user themes in {dir:?}");
+        return themes;
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("ron")))
+        .collect();
+    paths.sort();
+
+    for path in paths {
+        let id = path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+        let theme = match read_to_string(&path)
+            .map_err(Into::into)
+            .and_then(|content| parse(&id, &content))
+        {
+            Ok(theme) => theme,
+            Err(err) => {
+                warn!("Skipping theme {path:?}: {err}");
+                continue;
+            }
+        };
+        match themes.iter_mut().find(|other| other.id == id) {
+            Some(other) => *other = theme,
+            None => themes.push(theme),
+        }
+    }
+    themes
+}